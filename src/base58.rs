@@ -0,0 +1,67 @@
+//! Base58 (de)serialization of fixed-size byte arrays, for use with
+//! `#[serde(with = "...")]` so keys and signatures read like on explorers.
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+pub(crate) fn serialize<S: Serializer, const N: usize>(
+    bytes: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&bs58::encode(bytes).into_string())
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    decode(&String::deserialize(deserializer)?)
+}
+
+/// Decodes a base58 string into an array of exactly `N` bytes.
+fn decode<E: Error, const N: usize>(encoded: &str) -> Result<[u8; N], E> {
+    let bytes = bs58::decode(encoded).into_vec().map_err(E::custom)?;
+    <[u8; N]>::try_from(bytes.as_slice())
+        .map_err(|_| E::invalid_length(bytes.len(), &"a base58-encoded byte array"))
+}
+
+/// Same as the parent module, for optional arrays.
+pub(crate) mod option {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer, const N: usize>(
+        bytes: &Option<[u8; N]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        bytes
+            .map(|b| bs58::encode(b).into_string())
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Option<[u8; N]>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|encoded| decode(&encoded))
+            .transpose()
+    }
+}
+
+/// Same as the parent module, for lists of arrays.
+pub(crate) mod vec {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer, const N: usize>(
+        bytes: &[[u8; N]],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(bytes.iter().map(|b| bs58::encode(b).into_string()))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Vec<[u8; N]>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|encoded| decode(encoded))
+            .collect()
+    }
+}
@@ -0,0 +1,513 @@
+use std::{fmt, time::Duration};
+
+use helius_laserstream::{
+    grpc::SubscribeRequestFilterTransactions, ChannelOptions, CompressionEncoding,
+};
+
+use crate::channels::{DlpSyncChannelsBroadcast, DlpSyncChannelsInit, DlpSyncChannelsRequester};
+use crate::config::{BackoffConfig, Backpressure, DlpSyncConfig};
+use crate::syncer::DlpSyncer;
+use crate::types::{AccountUpdate, Commitment, DlpSyncError, Pubkey, Slot};
+
+/// Builder for configuring and starting a [`DlpSyncer`].
+///
+/// Obtained via [`DlpSyncer::builder`].
+#[derive(Default)]
+pub struct DlpSyncerBuilder {
+    endpoints: Vec<String>,
+    api_key: String,
+    config: DlpSyncConfig,
+}
+
+impl fmt::Debug for DlpSyncerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DlpSyncerBuilder")
+            .field("endpoints", &self.endpoints)
+            .field("api_key", &"<redacted>")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl DlpSyncerBuilder {
+    /// Sets the Laserstream gRPC endpoint URL.
+    ///
+    /// Replaces any endpoints set before.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoints = vec![endpoint.into()];
+        self
+    }
+
+    /// Sets several Laserstream gRPC endpoint URLs, in order of preference.
+    ///
+    /// See [`DlpSyncer::start_with_endpoints`] for the failover semantics.
+    pub fn endpoints<I, S>(mut self, endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.endpoints = endpoints.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the API key used for authentication.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = api_key.into();
+        self
+    }
+
+    /// Replaces the whole runtime configuration.
+    ///
+    /// Setters called afterwards adjust the given configuration.
+    pub fn config(mut self, config: DlpSyncConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the capacity of the subscription request channel.
+    pub fn request_capacity(mut self, capacity: usize) -> Self {
+        self.config.request_capacity = capacity;
+        self
+    }
+
+    /// Sets the maximum number of tracked delegation records.
+    ///
+    /// See [`DlpSyncConfig::max_subscriptions`].
+    pub fn max_subscriptions(mut self, limit: usize) -> Self {
+        self.config.max_subscriptions = limit;
+        self
+    }
+
+    /// Sets the capacity of the update channel.
+    pub fn update_capacity(mut self, capacity: usize) -> Self {
+        self.config.update_capacity = capacity;
+        self
+    }
+
+    /// Sets the maximum number of reconnection attempts.
+    pub fn max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.config.max_reconnect_attempts = attempts;
+        self
+    }
+
+    /// Sets how long to wait for the first update after connecting.
+    ///
+    /// See [`DlpSyncConfig::health_check_timeout`].
+    pub fn health_check_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.health_check_timeout = timeout;
+        self
+    }
+
+    /// Sets the upper bound on a subscribe round-trip.
+    ///
+    /// See [`DlpSyncConfig::request_timeout`].
+    pub fn request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.request_timeout = timeout;
+        self
+    }
+
+    /// Sets how long the stream may stay silent before it is re-established.
+    ///
+    /// See [`DlpSyncConfig::stall_timeout`].
+    pub fn stall_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.stall_timeout = timeout;
+        self
+    }
+
+    /// Emits a heartbeat update at the given interval.
+    ///
+    /// See [`DlpSyncConfig::heartbeat_interval`].
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.config.heartbeat_interval = Some(interval);
+        self
+    }
+
+    /// Sets the maximum number of server-side filter updates per second.
+    ///
+    /// See [`DlpSyncConfig::filter_update_rate`].
+    pub fn filter_update_rate(mut self, rate: Option<u32>) -> Self {
+        self.config.filter_update_rate = rate;
+        self
+    }
+
+    /// Sets the backoff between reconnects of a stalled stream.
+    ///
+    /// See [`DlpSyncConfig::backoff`].
+    pub fn backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.config.backoff = backoff;
+        self
+    }
+
+    /// Enables or disables replay of missed updates on reconnect.
+    pub fn replay(mut self, enabled: bool) -> Self {
+        self.config.replay = enabled;
+        self
+    }
+
+    /// Starts streaming from the given slot rather than live.
+    ///
+    /// See [`DlpSyncConfig::from_slot`].
+    pub fn from_slot(mut self, slot: Slot) -> Self {
+        self.config.from_slot = Some(slot);
+        self
+    }
+
+    /// Compresses the connection in both directions with the given encoding.
+    ///
+    /// See [`DlpSyncConfig::accept_compression`] for the tradeoff.
+    pub fn compression(mut self, encoding: CompressionEncoding) -> Self {
+        self.config.accept_compression = vec![encoding];
+        self.config.send_compression = Some(encoding);
+        self
+    }
+
+    /// Sets the compression encodings the server may use for the stream.
+    ///
+    /// See [`DlpSyncConfig::accept_compression`].
+    pub fn accept_compression(mut self, encodings: Vec<CompressionEncoding>) -> Self {
+        self.config.accept_compression = encodings;
+        self
+    }
+
+    /// Sets the compression encoding of subscription requests.
+    ///
+    /// See [`DlpSyncConfig::send_compression`].
+    pub fn send_compression(mut self, encoding: Option<CompressionEncoding>) -> Self {
+        self.config.send_compression = encoding;
+        self
+    }
+
+    /// Sets the transport tuning of the gRPC channel.
+    ///
+    /// See [`DlpSyncConfig::channel_options`].
+    pub fn channel_options(mut self, options: ChannelOptions) -> Self {
+        self.config.channel_options = options;
+        self
+    }
+
+    /// Sets the commitment level of the subscription.
+    ///
+    /// See [`DlpSyncConfig::commitment`].
+    pub fn commitment(mut self, commitment: Commitment) -> Self {
+        self.config.commitment = Some(commitment);
+        self
+    }
+
+    /// Enables firehose mode, emitting every delegation and undelegation of
+    /// the program without requiring subscriptions.
+    ///
+    /// Beware of the bandwidth: on a busy cluster this is a steady stream of
+    /// updates, which are dropped if the consumer falls behind.
+    pub fn firehose(mut self, enabled: bool) -> Self {
+        self.config.firehose = enabled;
+        self
+    }
+
+    /// Logs detected events instead of emitting them.
+    ///
+    /// See [`DlpSyncConfig::dry_run`].
+    pub fn dry_run(mut self, enabled: bool) -> Self {
+        self.config.dry_run = enabled;
+        self
+    }
+
+    /// Bounds the total time [`build`](Self::build) may spend connecting.
+    ///
+    /// If exceeded, startup fails with `DlpSyncError::Connection("startup timed out")`.
+    pub fn start_timeout(mut self, timeout: Duration) -> Self {
+        self.config.start_timeout = Some(timeout);
+        self
+    }
+
+    /// Reports undelegations once per source that detected them.
+    ///
+    /// See [`DlpSyncConfig::dual_source_undelegations`].
+    pub fn dual_source_undelegations(mut self, enabled: bool) -> Self {
+        self.config.dual_source_undelegations = enabled;
+        self
+    }
+
+    /// Delays `Undelegated` events until their slot reaches the given commitment.
+    ///
+    /// See [`DlpSyncConfig::undelegation_commitment`].
+    pub fn undelegation_commitment(mut self, commitment: Commitment) -> Self {
+        self.config.undelegation_commitment = Some(commitment);
+        self
+    }
+
+    /// Adds an extra transaction filter to the subscription under the given label.
+    ///
+    /// Matching transactions are forwarded unparsed as
+    /// [`AccountUpdate::RawTransaction`](crate::AccountUpdate::RawTransaction).
+    /// The `undelegations` label is reserved and will be ignored.
+    pub fn transaction_filter(
+        mut self,
+        label: impl Into<String>,
+        filter: SubscribeRequestFilterTransactions,
+    ) -> Self {
+        self.config
+            .extra_transaction_filters
+            .insert(label.into(), filter);
+        self
+    }
+
+    /// Sets the accepted undelegate instruction discriminators.
+    ///
+    /// See [`DlpSyncConfig::undelegate_discriminators`].
+    pub fn undelegate_discriminators(mut self, discriminators: Vec<Vec<u8>>) -> Self {
+        self.config.undelegate_discriminators = discriminators;
+        self
+    }
+
+    /// Sets the position of the delegation record among the undelegate instruction accounts.
+    ///
+    /// See [`DlpSyncConfig::undelegate_record_index`].
+    pub fn undelegate_record_index(mut self, index: usize) -> Self {
+        self.config.undelegate_record_index = index;
+        self
+    }
+
+    /// Emits slot status transitions as updates.
+    ///
+    /// See [`DlpSyncConfig::slot_status_updates`].
+    pub fn slot_status_updates(mut self, enabled: bool) -> Self {
+        self.config.slot_status_updates = enabled;
+        self
+    }
+
+    /// Delivers stream updates in slot order, once their slot is confirmed.
+    ///
+    /// See [`DlpSyncConfig::slot_ordered`].
+    pub fn slot_ordered(mut self, enabled: bool) -> Self {
+        self.config.slot_ordered = enabled;
+        self
+    }
+
+    /// Sets the slot jump above which a gap is reported.
+    ///
+    /// See [`DlpSyncConfig::slot_gap_threshold`].
+    pub fn slot_gap_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.config.slot_gap_threshold = threshold;
+        self
+    }
+
+    /// Emits every stream update unmodified, alongside the typed updates.
+    ///
+    /// See [`DlpSyncConfig::raw_passthrough`].
+    pub fn raw_passthrough(mut self, enabled: bool) -> Self {
+        self.config.raw_passthrough = enabled;
+        self
+    }
+
+    /// Sets a warm-up grace period after connecting.
+    ///
+    /// See [`DlpSyncConfig::warmup`].
+    pub fn warmup(mut self, warmup: Duration) -> Self {
+        self.config.warmup = warmup;
+        self
+    }
+
+    /// Suppresses replayed undelegation transactions by remembering recent signatures.
+    ///
+    /// See [`DlpSyncConfig::signature_dedup_capacity`].
+    pub fn signature_dedup(mut self, capacity: usize) -> Self {
+        self.config.signature_dedup_capacity = Some(capacity);
+        self
+    }
+
+    /// Suppresses repeated account updates by remembering the last state per record.
+    ///
+    /// See [`DlpSyncConfig::account_dedup_capacity`].
+    pub fn account_dedup(mut self, capacity: usize) -> Self {
+        self.config.account_dedup_capacity = Some(capacity);
+        self
+    }
+
+    /// Sets the policy applied when the update channel is full.
+    ///
+    /// Defaults to [`Backpressure::DropNewest`].
+    pub fn backpressure(mut self, policy: Backpressure) -> Self {
+        self.config.backpressure = policy;
+        self
+    }
+
+    /// Emits compact deltas of changed record fields instead of full record data.
+    ///
+    /// See [`DlpSyncConfig::delta_updates`].
+    pub fn delta_updates(mut self, enabled: bool) -> Self {
+        self.config.delta_updates = enabled;
+        self
+    }
+
+    /// Emits decoded delegation records instead of raw account data.
+    ///
+    /// See [`DlpSyncConfig::parse_records`].
+    pub fn parse_records(mut self, enabled: bool) -> Self {
+        self.config.parse_records = enabled;
+        self
+    }
+
+    /// Tracks a single delegation program at the given address.
+    ///
+    /// See [`DlpSyncConfig::delegation_programs`].
+    pub fn delegation_program(mut self, program: Pubkey) -> Self {
+        self.config.delegation_programs = vec![program];
+        self
+    }
+
+    /// Tracks several delegation programs at once.
+    ///
+    /// See [`DlpSyncConfig::delegation_programs`].
+    pub fn delegation_programs(mut self, programs: Vec<Pubkey>) -> Self {
+        self.config.delegation_programs = programs;
+        self
+    }
+
+    /// Sets the delegation record data size filter, or removes it with `None`.
+    ///
+    /// See [`DlpSyncConfig::record_size`].
+    pub fn record_size(mut self, size: Option<u64>) -> Self {
+        self.config.record_size = size;
+        self
+    }
+
+    /// Also reports undelegation attempts that failed on-chain.
+    ///
+    /// See [`DlpSyncConfig::include_failed_undelegations`].
+    pub fn include_failed_undelegations(mut self, enabled: bool) -> Self {
+        self.config.include_failed_undelegations = enabled;
+        self
+    }
+
+    /// Reports the undelegations of one transaction as a single update.
+    ///
+    /// See [`DlpSyncConfig::batch_undelegations`].
+    pub fn batch_undelegations(mut self, enabled: bool) -> Self {
+        self.config.batch_undelegations = enabled;
+        self
+    }
+
+    /// Unsubscribes records once they undelegate.
+    ///
+    /// See [`DlpSyncConfig::auto_unsubscribe_on_undelegate`].
+    pub fn auto_unsubscribe_on_undelegate(mut self, enabled: bool) -> Self {
+        self.config.auto_unsubscribe_on_undelegate = enabled;
+        self
+    }
+
+    /// Drops subscriptions whose record saw no activity for the given duration.
+    ///
+    /// See [`DlpSyncConfig::subscription_ttl`].
+    pub fn subscription_ttl(mut self, ttl: Duration) -> Self {
+        self.config.subscription_ttl = Some(ttl);
+        self
+    }
+
+    /// Emits an initial snapshot of the delegations seen during the given window.
+    ///
+    /// See [`DlpSyncConfig::bootstrap_window`].
+    pub fn bootstrap_snapshot(mut self, window: Duration) -> Self {
+        self.config.bootstrap_window = Some(window);
+        self
+    }
+
+    /// Connects to the Laserstream and spawns the syncer.
+    ///
+    /// Fails without connecting if the endpoint, API key or delegation program
+    /// list is empty.
+    /// See [`DlpSyncer::start`] for details.
+    pub async fn build(self) -> Result<DlpSyncChannelsInit, DlpSyncError> {
+        if self.endpoints.is_empty() || self.endpoints.iter().any(String::is_empty) {
+            return Err(DlpSyncError::Connection("endpoint is empty"));
+        }
+        if self.api_key.is_empty() {
+            return Err(DlpSyncError::Connection("api key is empty"));
+        }
+        if self.config.delegation_programs.is_empty() {
+            return Err(DlpSyncError::Connection("no delegation program configured"));
+        }
+
+        DlpSyncer::start_with_endpoints(self.endpoints, self.api_key, self.config).await
+    }
+
+    /// Like [`build`](Self::build), but runs `handler` on every update.
+    ///
+    /// See [`DlpSyncer::start_with_handler`].
+    pub async fn build_with_handler<F>(
+        self,
+        handler: F,
+    ) -> Result<DlpSyncChannelsRequester, DlpSyncError>
+    where
+        F: FnMut(AccountUpdate) + Send + 'static,
+    {
+        Ok(self.build().await?.split_with_handler(handler))
+    }
+
+    /// Like [`build`](Self::build), but fans the updates out over a broadcast
+    /// channel with the configured update capacity.
+    ///
+    /// See [`DlpSyncChannelsInit::into_broadcast`].
+    pub async fn build_broadcast(self) -> Result<DlpSyncChannelsBroadcast, DlpSyncError> {
+        let capacity = self.config.update_capacity;
+        Ok(self.build().await?.into_broadcast(capacity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_incomplete_settings_without_connecting() {
+        let complete = || {
+            DlpSyncer::builder()
+                .endpoint("http://localhost:1")
+                .api_key("key")
+        };
+        for (builder, expected) in [
+            (complete().endpoint(""), "endpoint is empty"),
+            (
+                complete().endpoints(Vec::<String>::new()),
+                "endpoint is empty",
+            ),
+            (complete().api_key(""), "api key is empty"),
+            (
+                complete().config(DlpSyncConfig {
+                    delegation_programs: Vec::new(),
+                    ..Default::default()
+                }),
+                "no delegation program configured",
+            ),
+        ] {
+            assert!(matches!(
+                builder.build().await,
+                Err(DlpSyncError::Connection(message)) if message == expected
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn config_snapshot_reflects_builder_settings() {
+        let builder = DlpSyncer::builder()
+            .endpoint("https://localhost:443")
+            .api_key("secret")
+            .max_subscriptions(16)
+            .update_capacity(32)
+            .commitment(Commitment::Confirmed)
+            .firehose(true)
+            .dry_run(true);
+        assert!(!format!("{builder:?}").contains("secret"));
+
+        let (channels, _local) = DlpSyncer::start_local(builder.config);
+        let (requester, _updates) = channels.split();
+        let snapshot = requester.config_snapshot().await.unwrap();
+
+        assert_eq!(snapshot.endpoint, "local");
+        assert_eq!(snapshot.max_subscriptions, 16);
+        assert_eq!(snapshot.update_capacity, 32);
+        assert_eq!(snapshot.commitment, Some(Commitment::Confirmed));
+        assert!(snapshot.firehose);
+        assert!(snapshot.dry_run);
+        assert!(!format!("{snapshot:?}").contains("secret"));
+    }
+}
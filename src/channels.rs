@@ -1,23 +1,71 @@
-use tokio::sync::mpsc::Receiver;
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    time::Duration,
+};
 
+use futures::Stream;
+use tokio::{
+    sync::{broadcast, mpsc::Receiver, watch},
+    time,
+};
+
+use crate::config::ConfigSnapshot;
+use crate::guard::SubscriptionGuard;
+use crate::handle::DlpSyncHandle;
+use crate::metrics::SyncMetrics;
+use crate::record::derive_delegation_record;
 use crate::syncer::SyncRequest;
-use crate::types::{AccountUpdate, Pubkey, Slot};
+use crate::types::{
+    AccountUpdate, Commitment, HealthStatus, Pubkey, RequesterError, Slot, SubscribeResult,
+};
 
 /// Generic channels container for communicating with a `DlpSyncer`.
 ///
 /// The `R` type parameter allows for different channel configurations
 /// depending on usage context.
+#[derive(Debug)]
 pub struct DlpSyncChannels<R> {
     pub(crate) requests: tokio::sync::mpsc::Sender<SyncRequest>,
     pub(crate) updates: R,
+    pub(crate) connected: watch::Receiver<bool>,
+    /// Upper bound on subscribe round-trips.
+    pub(crate) request_timeout: Option<Duration>,
+    /// Delegation programs the syncer tracks, to derive record addresses under.
+    pub(crate) delegation_programs: Arc<[Pubkey]>,
+    /// Counters and gauges maintained by the syncer.
+    pub(crate) metrics: Arc<SyncMetrics>,
+    /// The spawned syncer task, only held by freshly initialized channels.
+    pub(crate) task: Option<DlpSyncHandle>,
 }
 
 /// Initialized channel pair with both request and update sides.
 pub type DlpSyncChannelsInit = DlpSyncChannels<Receiver<AccountUpdate>>;
 
 /// Requester-only channel pair for sending subscription requests.
+///
+/// Cloning is cheap; clones share the same syncer.
 pub type DlpSyncChannelsRequester = DlpSyncChannels<()>;
 
+/// Initialized channel pair whose updates are fanned out to multiple consumers.
+///
+/// See [`DlpSyncChannelsInit::into_broadcast`].
+pub type DlpSyncChannelsBroadcast = DlpSyncChannels<broadcast::Sender<AccountUpdate>>;
+
+impl Clone for DlpSyncChannelsRequester {
+    fn clone(&self) -> Self {
+        Self {
+            requests: self.requests.clone(),
+            updates: (),
+            connected: self.connected.clone(),
+            request_timeout: self.request_timeout,
+            delegation_programs: self.delegation_programs.clone(),
+            metrics: self.metrics.clone(),
+            task: None,
+        }
+    }
+}
+
 impl DlpSyncChannelsRequester {
     /// Subscribe to updates for a delegation record.
     ///
@@ -27,33 +75,462 @@ impl DlpSyncChannelsRequester {
     ///
     /// # Returns
     ///
-    /// Returns the current slot number if the subscription was successful, or `None`
-    /// if the sync service has terminated or the channel is closed.
-    pub async fn subscribe(&self, record: Pubkey) -> Option<Slot> {
+    /// Returns the latest observed slot number if the subscription was successful,
+    /// or a [`RequesterError`] if the sync service has terminated, dropped the
+    /// request, did not respond within the configured request timeout, or
+    /// the subscription limit was reached.
+    /// The latest observed slot may be unconfirmed; see [`subscribe_at`](Self::subscribe_at).
+    pub async fn subscribe(&self, record: Pubkey) -> Result<Slot, RequesterError> {
+        Ok(self.subscribe_inner(record, None).await?.slot)
+    }
+
+    /// Subscribe to updates for a delegation record, also reporting whether
+    /// the record is known to be delegated.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The pubkey of the delegation record to subscribe to.
+    ///
+    /// # Returns
+    ///
+    /// Returns a [`SubscribeResult`] with the latest observed slot if the
+    /// subscription was successful, or a [`RequesterError`] as for
+    /// [`subscribe`](Self::subscribe).
+    pub async fn subscribe_with_state(
+        &self,
+        record: Pubkey,
+    ) -> Result<SubscribeResult, RequesterError> {
+        self.subscribe_inner(record, None).await
+    }
+
+    /// Subscribe to updates for a delegation record, reporting the slot at a
+    /// given commitment.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The pubkey of the delegation record to subscribe to.
+    /// * `commitment` - The commitment level of the reported slot.
+    ///
+    /// # Returns
+    ///
+    /// Returns the latest slot at `commitment` if the subscription was successful,
+    /// or a [`RequesterError`] as for [`subscribe`](Self::subscribe).
+    pub async fn subscribe_at(
+        &self,
+        record: Pubkey,
+        commitment: Commitment,
+    ) -> Result<Slot, RequesterError> {
+        Ok(self.subscribe_inner(record, Some(commitment)).await?.slot)
+    }
+
+    async fn subscribe_inner(
+        &self,
+        record: Pubkey,
+        commitment: Option<Commitment>,
+    ) -> Result<SubscribeResult, RequesterError> {
+        let (slot_tx, rx) = tokio::sync::oneshot::channel();
+        self.round_trip(
+            SyncRequest::Subscribe {
+                record,
+                commitment,
+                slot_tx,
+            },
+            rx,
+        )
+        .await?
+    }
+
+    /// Sends a request and awaits its response, bounded by the request timeout.
+    async fn round_trip<T>(
+        &self,
+        request: SyncRequest,
+        rx: tokio::sync::oneshot::Receiver<T>,
+    ) -> Result<T, RequesterError> {
+        let request = async {
+            self.requests
+                .send(request)
+                .await
+                .map_err(|_| RequesterError::SyncerGone)?;
+            rx.await.map_err(|_| RequesterError::ResponseDropped)
+        };
+
+        match self.request_timeout {
+            Some(limit) => time::timeout(limit, request)
+                .await
+                .map_err(|_| RequesterError::Timeout)?,
+            None => request.await,
+        }
+    }
+
+    /// Subscribe to updates for the delegation records of a delegated account.
+    ///
+    /// The record address is derived with [`derive_delegation_record`] under
+    /// each configured delegation program, see
+    /// [`DlpSyncConfig::delegation_programs`](crate::DlpSyncConfig::delegation_programs),
+    /// and the records are subscribed to as a single batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The pubkey of the delegated account.
+    ///
+    /// # Returns
+    ///
+    /// Returns the derived record pubkeys, in the order of the configured
+    /// programs, to match updates against, with the latest observed slot if
+    /// the subscription was successful, or a [`RequesterError`] as for
+    /// [`subscribe_many`](Self::subscribe_many).
+    pub async fn subscribe_delegated_account(
+        &self,
+        account: Pubkey,
+    ) -> Result<(Vec<Pubkey>, Slot), RequesterError> {
+        let records: Vec<Pubkey> = self
+            .delegation_programs
+            .iter()
+            .map(|program| derive_delegation_record(&account, program))
+            .collect();
+        let slot = self.subscribe_many(&records).await?;
+        Ok((records, slot))
+    }
+
+    /// Subscribe to updates for a delegation record for as long as the
+    /// returned guard lives.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The pubkey of the delegation record to subscribe to.
+    ///
+    /// # Returns
+    ///
+    /// Returns a [`SubscriptionGuard`] that unsubscribes the record when dropped,
+    /// for every holder of the record, or a [`RequesterError`] as for
+    /// [`subscribe`](Self::subscribe).
+    pub async fn subscribe_guarded(
+        &self,
+        record: Pubkey,
+    ) -> Result<SubscriptionGuard, RequesterError> {
+        let slot = self.subscribe(record).await?;
+        Ok(SubscriptionGuard {
+            requester: self.clone(),
+            record,
+            slot,
+        })
+    }
+
+    /// Subscribe to updates for several delegation records in a single request.
+    ///
+    /// Much cheaper than one [`subscribe`](Self::subscribe) per record when
+    /// bootstrapping a large watch set, as the server-side filter is updated
+    /// once for the whole batch. The batch is rejected as a whole if it would
+    /// exceed the subscription limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `records` - The pubkeys of the delegation records to subscribe to.
+    ///
+    /// # Returns
+    ///
+    /// Returns the latest observed slot number if the subscription was successful,
+    /// or a [`RequesterError`] as for [`subscribe`](Self::subscribe).
+    pub async fn subscribe_many(&self, records: &[Pubkey]) -> Result<Slot, RequesterError> {
         let (slot_tx, rx) = tokio::sync::oneshot::channel();
+        let result = self
+            .round_trip(
+                SyncRequest::SubscribeMany {
+                    records: records.to_vec(),
+                    slot_tx,
+                },
+                rx,
+            )
+            .await??;
+        Ok(result.slot)
+    }
+
+    /// Unsubscribe from a delegation record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The pubkey of the delegation record to unsubscribe from.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the unsubscribe request was sent successfully,
+    /// or [`RequesterError::SyncerGone`] if the sync service has terminated.
+    pub async fn unsubscribe(&self, record: Pubkey) -> Result<(), RequesterError> {
         self.requests
-            .send(SyncRequest::Subscribe { record, slot_tx })
+            .send(SyncRequest::Unsubscribe(record))
+            .await
+            .map_err(|_| RequesterError::SyncerGone)
+    }
+
+    /// Unsubscribe from every delegation record, e.g. to reset the watch set.
+    ///
+    /// The server-side filter is updated to no longer stream any record.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the request was sent successfully,
+    /// or [`RequesterError::SyncerGone`] if the sync service has terminated.
+    pub async fn unsubscribe_all(&self) -> Result<(), RequesterError> {
+        self.requests
+            .send(SyncRequest::UnsubscribeAll)
+            .await
+            .map_err(|_| RequesterError::SyncerGone)
+    }
+
+    /// Looks up the current state of a delegation record.
+    ///
+    /// State is only kept for subscribed records, from the first account
+    /// update received after subscribing until the record undelegates.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The delegation record pubkey.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Some((data, slot)))` with the latest record data and the
+    /// slot it was updated at, `Some(None)` if the record is not known to be
+    /// delegated, or `None` if the sync service has terminated or the channel
+    /// is closed.
+    pub async fn get_state(&self, record: Pubkey) -> Option<Option<(Vec<u8>, Slot)>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(SyncRequest::GetState { record, tx })
             .await
             .ok()?;
         rx.await.ok()
     }
 
-    /// Unsubscribe from a delegation record.
+    /// Takes a consistent copy of the state of every known delegated record.
+    ///
+    /// The copy reflects the state as of the latest update processed by the
+    /// syncer, and covers the same records as [`get_state`](Self::get_state).
+    ///
+    /// # Returns
+    ///
+    /// Returns the latest observed slot at the time of the snapshot along with
+    /// the `(record, data, slot)` of each record in no particular order, or
+    /// `None` if the sync service has terminated or the channel is closed.
+    pub async fn snapshot(&self) -> Option<(Slot, Vec<(Pubkey, Vec<u8>, Slot)>)> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(SyncRequest::Snapshot { tx })
+            .await
+            .ok()?;
+        rx.await.ok()
+    }
+
+    /// Lists the delegation records the syncer currently tracks.
+    ///
+    /// Subscriptions still buffered during the warm-up period are not included.
+    ///
+    /// # Returns
+    ///
+    /// Returns a snapshot of the subscription set in no particular order,
+    /// or `None` if the sync service has terminated or the channel is closed.
+    pub async fn list_subscriptions(&self) -> Option<Vec<Pubkey>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(SyncRequest::ListSubscriptions { tx })
+            .await
+            .ok()?;
+        rx.await.ok()
+    }
+
+    /// Reads the latest slot observed by the syncer, without subscribing.
+    ///
+    /// Polling this is a cheap way to check that the stream is still advancing.
+    ///
+    /// # Returns
+    ///
+    /// Returns the current slot number, or `None` if the sync service has
+    /// terminated or the channel is closed.
+    pub async fn current_slot(&self) -> Option<Slot> {
+        self.current_slot_inner(None).await
+    }
+
+    /// Reads the latest slot the syncer observed at the given commitment.
     ///
     /// # Arguments
     ///
-    /// * `record` - The pubkey of the delegation record to unsubscribe from.
+    /// * `commitment` - The commitment level of the reported slot.
     ///
     /// # Returns
     ///
-    /// Returns `Some(())` if the unsubscribe request was sent successfully,
+    /// Returns the latest slot at `commitment` (zero if none was observed yet),
     /// or `None` if the sync service has terminated or the channel is closed.
-    pub async fn unsubscribe(&self, record: Pubkey) -> Option<()> {
+    pub async fn current_slot_at(&self, commitment: Commitment) -> Option<Slot> {
+        self.current_slot_inner(Some(commitment)).await
+    }
+
+    async fn current_slot_inner(&self, commitment: Option<Commitment>) -> Option<Slot> {
+        let (slot_tx, rx) = tokio::sync::oneshot::channel();
         self.requests
-            .send(SyncRequest::Unsubscribe(record))
+            .send(SyncRequest::CurrentSlot {
+                commitment,
+                slot_tx,
+            })
+            .await
+            .ok()?;
+        rx.await.ok()
+    }
+
+    /// Waits until the syncer is connected to the Laserstream.
+    ///
+    /// Resolves immediately if the connection is live, otherwise once it is
+    /// re-established after a stream error.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(())` once connected, or `None` if the timeout elapsed
+    /// or the sync service has terminated.
+    pub async fn await_connected(&self, timeout: Duration) -> Option<()> {
+        let mut connected = self.connected.clone();
+        time::timeout(timeout, connected.wait_for(|&c| c))
+            .await
+            .ok()?
+            .ok()?;
+        Some(())
+    }
+
+    /// Returns the syncer's counters and gauges.
+    ///
+    /// The metrics are shared with the syncer, so the returned handle can be
+    /// kept by an exporter and read at any time without a round-trip.
+    pub fn metrics(&self) -> Arc<SyncMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Queries the syncer's health.
+    ///
+    /// # Returns
+    ///
+    /// Returns a [`HealthStatus`] snapshot, including the span of slots observed
+    /// during this session, or `None` if the sync service has terminated or
+    /// the channel is closed.
+    pub async fn health(&self) -> Option<HealthStatus> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.requests.send(SyncRequest::Health(tx)).await.ok()?;
+        rx.await.ok()
+    }
+
+    /// Checks that the syncer is alive and its stream is current.
+    ///
+    /// Cheap enough for a supervisor's liveness probe. Slot updates keep a
+    /// healthy stream busy, so a staleness of a few seconds is a reasonable bound.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_staleness` - How long ago the stream may have last produced an item.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the stream produced an item within `max_staleness`,
+    /// or `false` if it did not or the sync service has terminated.
+    pub async fn is_healthy(&self, max_staleness: Duration) -> bool {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        if self
+            .requests
+            .send(SyncRequest::LastActivity(tx))
+            .await
+            .is_err()
+        {
+            return false;
+        }
+        rx.await.is_ok_and(|elapsed| elapsed <= max_staleness)
+    }
+
+    /// Queries the syncer's effective configuration.
+    ///
+    /// # Returns
+    ///
+    /// Returns a [`ConfigSnapshot`] with the API key omitted and the endpoint
+    /// redacted, or `None` if the sync service has terminated or the channel is closed.
+    pub async fn config_snapshot(&self) -> Option<ConfigSnapshot> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(SyncRequest::ConfigSnapshot(tx))
+            .await
+            .ok()?;
+        rx.await.ok()
+    }
+
+    /// Installs a fresh update channel on the syncer, e.g. after the previous
+    /// consumer task died.
+    ///
+    /// Updates still buffered in the old channel are lost, and the old
+    /// receiver sees the channel close.
+    ///
+    /// # Returns
+    ///
+    /// Returns the new update receiver, or `None` if the sync service has
+    /// terminated or the channel is closed.
+    pub async fn reconnect_updates(&self) -> Option<Receiver<AccountUpdate>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(SyncRequest::ReplaceUpdates(tx))
+            .await
+            .ok()?;
+        rx.await.ok()
+    }
+
+    /// Stops the syncer gracefully.
+    ///
+    /// Updates already queued by the syncer are delivered before the final
+    /// `SyncTerminated`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(())` if the shutdown request was sent successfully,
+    /// or `None` if the sync service has already terminated.
+    pub async fn shutdown(self) -> Option<()> {
+        self.requests
+            .send(SyncRequest::Shutdown { slot_tx: None })
             .await
             .ok()
     }
+
+    /// Stops the syncer gracefully and waits for it to wind down.
+    ///
+    /// Resolves once the updates already queued by the syncer have been
+    /// delivered, so the update receiver must keep being drained meanwhile.
+    /// The returned slot can be persisted to resume from on the next start.
+    ///
+    /// # Returns
+    ///
+    /// Returns the highest slot processed by the syncer, or `None` if the
+    /// sync service terminated before acknowledging the shutdown.
+    pub async fn close(self) -> Option<Slot> {
+        let (slot_tx, rx) = tokio::sync::oneshot::channel();
+        self.requests
+            .send(SyncRequest::Shutdown {
+                slot_tx: Some(slot_tx),
+            })
+            .await
+            .ok()?;
+        rx.await.ok()
+    }
+
+    /// Sets a slot floor below which the syncer drops all updates.
+    ///
+    /// Useful for consumers that persist a slot checkpoint, so that updates
+    /// replayed after a restart are filtered before reaching the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The lowest slot for which updates are emitted.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(())` if the request was sent successfully,
+    /// or `None` if the sync service has terminated or the channel is closed.
+    pub async fn set_min_slot(&self, slot: Slot) -> Option<()> {
+        self.requests.send(SyncRequest::SetMinSlot(slot)).await.ok()
+    }
 }
 
 impl DlpSyncChannelsInit {
@@ -68,7 +545,242 @@ impl DlpSyncChannelsInit {
         let requester = DlpSyncChannelsRequester {
             requests: self.requests,
             updates: (),
+            connected: self.connected,
+            request_timeout: self.request_timeout,
+            delegation_programs: self.delegation_programs.clone(),
+            metrics: self.metrics,
+            task: None,
         };
         (requester, self.updates)
     }
+
+    /// Like [`split`](Self::split), but hands every update to `handler` in a
+    /// spawned task instead of returning the receiver.
+    ///
+    /// See [`DlpSyncer::start_with_handler`](crate::DlpSyncer::start_with_handler)
+    /// for the backpressure and panic semantics.
+    pub fn split_with_handler<F>(self, mut handler: F) -> DlpSyncChannelsRequester
+    where
+        F: FnMut(AccountUpdate) + Send + 'static,
+    {
+        let (requester, mut updates) = self.split();
+        let metrics = requester.metrics();
+        tokio::spawn(async move {
+            while let Some(update) = updates.recv().await {
+                let terminated = matches!(update, AccountUpdate::SyncTerminated(_));
+                // Contain handler panics so one bad update does not stop delivery
+                if panic::catch_unwind(AssertUnwindSafe(|| handler(update))).is_err() {
+                    metrics.inc_callback_panics();
+                    tracing::error!("update handler panicked, skipping update");
+                }
+                if terminated {
+                    break;
+                }
+            }
+        });
+        requester
+    }
+
+    /// Like [`split`](Self::split), but exposes the updates as a [`Stream`].
+    ///
+    /// The stream ends right after yielding [`AccountUpdate::SyncTerminated`].
+    ///
+    /// # Returns
+    ///
+    /// A tuple of:
+    /// - [`DlpSyncChannelsRequester`] for sending subscription requests
+    /// - a [`Stream`] of updates
+    pub fn split_stream(
+        self,
+    ) -> (
+        DlpSyncChannelsRequester,
+        impl Stream<Item = AccountUpdate> + Send + 'static,
+    ) {
+        let (requester, updates) = self.split();
+        let stream = futures::stream::unfold(Some(updates), |updates| async move {
+            let mut updates = updates?;
+            let update = updates.recv().await?;
+            let next = match update {
+                AccountUpdate::SyncTerminated(_) => None,
+                _ => Some(updates),
+            };
+            Some((update, next))
+        });
+        (requester, stream)
+    }
+
+    /// Like [`split`](Self::split), but also returns the handle to the syncer task.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of:
+    /// - [`DlpSyncChannelsRequester`] for sending subscription requests
+    /// - [`Receiver<AccountUpdate>`] for receiving updates
+    /// - [`DlpSyncHandle`] for observing or aborting the syncer task
+    pub fn split_with_handle(
+        mut self,
+    ) -> (
+        DlpSyncChannelsRequester,
+        Receiver<AccountUpdate>,
+        DlpSyncHandle,
+    ) {
+        let handle = self
+            .task
+            .take()
+            .expect("initialized channels always carry the task handle");
+        let (requester, updates) = self.split();
+        (requester, updates, handle)
+    }
+
+    /// Fans the updates out to any number of consumers over a broadcast channel.
+    ///
+    /// A forwarding task moves every update from the update channel into a
+    /// [`broadcast`] channel of the given capacity; consumers obtain their own
+    /// receiver via [`broadcast::Sender::subscribe`]. Updates sent while no
+    /// receiver exists are discarded, so subscribe consumers before subscribing
+    /// records.
+    ///
+    /// Each receiver lags independently: one falling more than `capacity`
+    /// updates behind skips the oldest ones and observes
+    /// [`RecvError::Lagged`](broadcast::error::RecvError::Lagged). The
+    /// [`Backpressure`](crate::Backpressure) policy therefore no longer applies.
+    ///
+    /// [`AccountUpdate::SyncTerminated`] is always the last update broadcast,
+    /// and is delivered to every receiver subscribed at that time, even lagging
+    /// ones. Receivers only observe the channel as closed once every clone of
+    /// the returned sender is dropped as well.
+    pub fn into_broadcast(mut self, capacity: usize) -> DlpSyncChannelsBroadcast {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        let forward = sender.clone();
+        let mut updates = self.updates;
+        tokio::spawn(async move {
+            while let Some(update) = updates.recv().await {
+                let terminated = matches!(update, AccountUpdate::SyncTerminated(_));
+                // Without receivers the update is meant for nobody
+                let _ = forward.send(update);
+                if terminated {
+                    break;
+                }
+            }
+        });
+
+        DlpSyncChannels {
+            requests: self.requests,
+            updates: sender,
+            connected: self.connected,
+            request_timeout: self.request_timeout,
+            delegation_programs: self.delegation_programs.clone(),
+            metrics: self.metrics.clone(),
+            task: self.task.take(),
+        }
+    }
+}
+
+impl DlpSyncChannelsBroadcast {
+    /// Splits the channels into a requester and the broadcast update sender.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of:
+    /// - [`DlpSyncChannelsRequester`] for sending subscription requests
+    /// - [`broadcast::Sender<AccountUpdate>`] to subscribe update consumers
+    pub fn split(self) -> (DlpSyncChannelsRequester, broadcast::Sender<AccountUpdate>) {
+        let requester = DlpSyncChannelsRequester {
+            requests: self.requests,
+            updates: (),
+            connected: self.connected,
+            request_timeout: self.request_timeout,
+            delegation_programs: self.delegation_programs.clone(),
+            metrics: self.metrics,
+            task: None,
+        };
+        (requester, self.updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::config::DlpSyncConfig;
+    use crate::record::DELEGATION_RECORD_SIZE;
+    use crate::syncer::{DlpSyncer, DELEGATION_PROGRAM_PUBKEY};
+
+    /// A tracked delegation record.
+    const RECORD: Pubkey = [7; 32];
+
+    /// A delegated account.
+    const ACCOUNT: Pubkey = [1; 32];
+
+    /// A delegation program other than the default one.
+    const CUSTOM_PROGRAM: Pubkey = [9; 32];
+
+    #[tokio::test]
+    async fn handler_panics_do_not_stop_delivery() {
+        let (channels, local) = DlpSyncer::start_local(DlpSyncConfig::default());
+        let (seen_tx, mut seen) = mpsc::unbounded_channel();
+        let requester = channels.split_with_handler(move |update| {
+            if let AccountUpdate::Delegated { slot: 10, .. } = update {
+                panic!("handler failure");
+            }
+            let _ = seen_tx.send(update);
+        });
+        requester.subscribe(RECORD).await.unwrap();
+
+        let data = vec![1; DELEGATION_RECORD_SIZE as usize];
+        local.push_record(DELEGATION_PROGRAM_PUBKEY, RECORD, data.clone(), 10);
+        local.push_record(DELEGATION_PROGRAM_PUBKEY, RECORD, data, 11);
+
+        let update = time::timeout(Duration::from_secs(1), seen.recv()).await;
+        assert!(matches!(
+            update,
+            Ok(Some(AccountUpdate::Delegated { slot: 11, .. }))
+        ));
+        assert_eq!(requester.metrics().callback_panics(), 1);
+    }
+
+    #[tokio::test]
+    async fn subscribes_by_delegated_account() {
+        let (channels, _local) = DlpSyncer::start_local(DlpSyncConfig::default());
+        let (requester, _updates) = channels.split();
+
+        let (records, _) = requester
+            .subscribe_delegated_account(ACCOUNT)
+            .await
+            .unwrap();
+        assert_eq!(
+            records,
+            vec![derive_delegation_record(
+                &ACCOUNT,
+                &DELEGATION_PROGRAM_PUBKEY
+            )]
+        );
+        assert_eq!(requester.list_subscriptions().await, Some(records));
+    }
+
+    #[tokio::test]
+    async fn derives_records_under_each_configured_program() {
+        let config = DlpSyncConfig {
+            delegation_programs: vec![CUSTOM_PROGRAM, DELEGATION_PROGRAM_PUBKEY],
+            ..Default::default()
+        };
+        let (channels, _local) = DlpSyncer::start_local(config);
+        let (requester, _updates) = channels.split();
+
+        let (records, _) = requester
+            .subscribe_delegated_account(ACCOUNT)
+            .await
+            .unwrap();
+        let custom = derive_delegation_record(&ACCOUNT, &CUSTOM_PROGRAM);
+        let default = derive_delegation_record(&ACCOUNT, &DELEGATION_PROGRAM_PUBKEY);
+        assert_ne!(custom, default);
+        assert_eq!(records, vec![custom, default]);
+
+        let mut subscriptions = requester.list_subscriptions().await.unwrap();
+        subscriptions.sort();
+        let mut expected = records;
+        expected.sort();
+        assert_eq!(subscriptions, expected);
+    }
 }
@@ -0,0 +1,155 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
+
+/// Bounded set remembering the most recently inserted keys.
+///
+/// Once full, inserting a new key evicts the oldest one.
+pub(crate) struct RecentSet<T> {
+    keys: HashSet<T>,
+    order: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T: Hash + Eq + Clone> RecentSet<T> {
+    /// Creates an empty set holding at most `capacity` keys.
+    pub(crate) fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            keys: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Whether the key is currently remembered.
+    pub(crate) fn contains(&self, key: &T) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Inserts a key, returning `false` if it was already present.
+    pub(crate) fn insert(&mut self, key: T) -> bool {
+        if self.keys.contains(&key) {
+            return false;
+        }
+
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+
+        self.keys.insert(key.clone());
+        self.order.push_back(key);
+        true
+    }
+}
+
+/// Bounded map remembering the most recently written keys.
+///
+/// Once full, writing a new key evicts the least recently written one.
+pub(crate) struct RecentMap<K, V> {
+    entries: HashMap<K, (V, u64)>,
+    /// Write order, tagged with a write counter; entries overwritten since
+    /// are stale and skipped on eviction.
+    order: VecDeque<(K, u64)>,
+    writes: u64,
+    capacity: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> RecentMap<K, V> {
+    /// Creates an empty map holding at most `capacity` keys.
+    pub(crate) fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            writes: 0,
+            capacity,
+        }
+    }
+
+    /// The value currently remembered for the key, if any.
+    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    /// Writes a value for the key, returning the previous one if present.
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.writes += 1;
+        let previous = self.entries.insert(key.clone(), (value, self.writes));
+        self.order.push_back((key, self.writes));
+
+        while self.entries.len() > self.capacity {
+            let Some((oldest, write)) = self.order.pop_front() else {
+                break;
+            };
+            if self.is_current(&oldest, write) {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        // Drop stale order entries left behind by repeated writes to the same keys
+        if self.order.len() > self.capacity * 2 {
+            let entries = &self.entries;
+            self.order
+                .retain(|(key, write)| entries.get(key).is_some_and(|(_, w)| w == write));
+        }
+
+        previous.map(|(value, _)| value)
+    }
+
+    /// Forgets the key, returning its value if present.
+    pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(value, _)| value)
+    }
+
+    fn is_current(&self, key: &K, write: u64) -> bool {
+        self.entries.get(key).is_some_and(|(_, w)| *w == write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_rejects_repeats_and_evicts_the_oldest() {
+        let mut set = RecentSet::new(2);
+        assert!(set.insert(1));
+        assert!(!set.insert(1));
+        assert!(set.insert(2));
+        assert!(set.insert(3));
+
+        assert!(!set.contains(&1));
+        assert!(set.contains(&2) && set.contains(&3));
+        assert!(set.insert(1));
+    }
+
+    #[test]
+    fn map_evicts_the_least_recently_written() {
+        let mut map = RecentMap::new(2);
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("b", 2), None);
+        // Rewriting `a` makes `b` the least recently written
+        assert_eq!(map.insert("a", 3), Some(1));
+        map.insert("c", 4);
+
+        assert_eq!(map.get(&"a"), Some(&3));
+        assert_eq!(map.get(&"b"), None);
+        assert_eq!(map.get(&"c"), Some(&4));
+    }
+
+    #[test]
+    fn map_stays_bounded_under_repeated_writes() {
+        let mut map = RecentMap::new(2);
+        for write in 0..100 {
+            map.insert(write % 3, write);
+        }
+
+        assert_eq!(map.entries.len(), 2);
+        assert!(map.order.len() <= 4);
+        assert_eq!(map.remove(&(99 % 3)), Some(99));
+    }
+}
@@ -0,0 +1,50 @@
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::channels::DlpSyncChannelsRequester;
+use crate::syncer::SyncRequest;
+use crate::types::{Pubkey, Slot};
+
+/// Subscription that unsubscribes its record when dropped.
+///
+/// Obtained via [`DlpSyncChannelsRequester::subscribe_guarded`]. The
+/// unsubscribe on drop is fire-and-forget: it is queued without waiting for
+/// the syncer, and silently skipped if the syncer has already terminated.
+///
+/// The syncer does not count holders of a record, so dropping a guard cancels
+/// the subscription for everyone: other guards on the same record and plain
+/// [`subscribe`](DlpSyncChannelsRequester::subscribe) callers alike. Share a
+/// single guard, e.g. behind an `Arc`, when several tasks watch one record.
+#[derive(Debug)]
+pub struct SubscriptionGuard {
+    pub(crate) requester: DlpSyncChannelsRequester,
+    pub(crate) record: Pubkey,
+    pub(crate) slot: Slot,
+}
+
+impl SubscriptionGuard {
+    /// The guarded delegation record.
+    pub fn record(&self) -> Pubkey {
+        self.record
+    }
+
+    /// The slot reported when subscribing.
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        let request = SyncRequest::Unsubscribe(self.record);
+        // A full request channel only delays the unsubscribe, so hand it to a
+        // task if a runtime is around to run one
+        if let Err(TrySendError::Full(request)) = self.requester.requests.try_send(request) {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                let requests = self.requester.requests.clone();
+                runtime.spawn(async move {
+                    let _ = requests.send(request).await;
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use tokio::task::{JoinError, JoinHandle};
+
+/// Handle to the spawned syncer task.
+///
+/// Lets the caller observe termination (including panics) and abort the
+/// syncer. Dropping the handle detaches the task; it keeps running.
+#[derive(Debug)]
+pub struct DlpSyncHandle {
+    pub(crate) task: JoinHandle<()>,
+}
+
+impl DlpSyncHandle {
+    /// Whether the syncer task has finished.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Aborts the syncer task.
+    ///
+    /// A final `SyncTerminated` update is still attempted, but may be lost if
+    /// the update channel is full.
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
+    /// Waits for the syncer task to finish.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the task ended normally, or a [`JoinError`] if it
+    /// panicked or was aborted.
+    pub async fn join(self) -> Result<(), JoinError> {
+        self.task.await
+    }
+}
@@ -0,0 +1,114 @@
+/// Instructions of the delegation program, keyed by their discriminator.
+///
+/// Only [`Delegate`](Self::Delegate) and [`Undelegate`](Self::Undelegate)
+/// produce updates; in particular, committing or finalizing state leaves the
+/// record delegated and is never reported as an undelegation.
+///
+/// Only the record lifecycle instructions are named. The rest of the
+/// program's instructions (fee vaults, ephemeral balances, ...) are not
+/// relevant to delegation state and decode as [`Unknown`](Self::Unknown).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DlpInstruction {
+    /// Delegates an account, creating its delegation record.
+    Delegate,
+    /// Commits a new state of a delegated account.
+    CommitState,
+    /// Applies a committed state to a delegated account.
+    Finalize,
+    /// Returns a delegated account to its owner, closing its delegation record.
+    Undelegate,
+    /// Any other instruction of the program.
+    Unknown(u8),
+}
+
+impl DlpInstruction {
+    /// Classifies instruction data by its leading discriminator byte.
+    ///
+    /// Discriminators are encoded either as a single byte or as a
+    /// little-endian `u64`, both starting with the same byte. Returns `None`
+    /// for empty data.
+    pub fn from_data(data: &[u8]) -> Option<Self> {
+        Some(Self::from(*data.first()?))
+    }
+
+    /// The discriminator byte of the instruction.
+    pub fn discriminator(self) -> u8 {
+        match self {
+            Self::Delegate => 0,
+            Self::CommitState => 1,
+            Self::Finalize => 2,
+            Self::Undelegate => 3,
+            Self::Unknown(discriminator) => discriminator,
+        }
+    }
+}
+
+impl From<u8> for DlpInstruction {
+    fn from(discriminator: u8) -> Self {
+        match discriminator {
+            0 => Self::Delegate,
+            1 => Self::CommitState,
+            2 => Self::Finalize,
+            3 => Self::Undelegate,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_lifecycle_instructions() {
+        assert_eq!(
+            DlpInstruction::from_data(&[0]),
+            Some(DlpInstruction::Delegate)
+        );
+        assert_eq!(
+            DlpInstruction::from_data(&[1]),
+            Some(DlpInstruction::CommitState)
+        );
+        assert_eq!(
+            DlpInstruction::from_data(&[2]),
+            Some(DlpInstruction::Finalize)
+        );
+        assert_eq!(
+            DlpInstruction::from_data(&[3]),
+            Some(DlpInstruction::Undelegate)
+        );
+    }
+
+    #[test]
+    fn decodes_u64_discriminators() {
+        let data = [3, 0, 0, 0, 0, 0, 0, 0, 0xff];
+        assert_eq!(
+            DlpInstruction::from_data(&data),
+            Some(DlpInstruction::Undelegate)
+        );
+        let data = 1u64.to_le_bytes();
+        assert_eq!(
+            DlpInstruction::from_data(&data),
+            Some(DlpInstruction::CommitState)
+        );
+    }
+
+    #[test]
+    fn other_instructions_are_unknown() {
+        assert_eq!(
+            DlpInstruction::from_data(&[5]),
+            Some(DlpInstruction::Unknown(5))
+        );
+        assert_eq!(DlpInstruction::from_data(&[]), None);
+    }
+
+    #[test]
+    fn discriminator_round_trips() {
+        for discriminator in 0..=u8::MAX {
+            assert_eq!(
+                DlpInstruction::from(discriminator).discriminator(),
+                discriminator
+            );
+        }
+    }
+}
@@ -6,9 +6,9 @@
 //! # Usage
 //!
 //! ```no_run
-//! use dlp_sync::DlpSyncer;
+//! use magicblock_sync::DlpSyncer;
 //!
-//! # async fn example() -> Result<(), dlp_sync::DlpSyncError> {
+//! # async fn example() -> Result<(), magicblock_sync::DlpSyncError> {
 //! let channels = DlpSyncer::start(
 //!     "http://localhost:8000".to_string(),
 //!     "your-api-key".to_string()
@@ -18,30 +18,84 @@
 //!
 //! // Subscribe to a delegation record
 //! let pubkey = [0u8; 32];
-//! if let Some(slot) = requester.subscribe(pubkey).await {
+//! if let Ok(slot) = requester.subscribe(pubkey).await {
 //!     println!("Subscribed at slot: {}", slot);
 //! }
 //!
 //! // Receive updates
 //! while let Some(update) = updates.recv().await {
 //!     match update {
-//!         dlp_sync::AccountUpdate::Delegated { record, slot, .. } => {
+//!         magicblock_sync::AccountUpdate::Delegated { record, slot, .. } => {
 //!             println!("Delegation at slot {}", slot);
 //!         }
-//!         dlp_sync::AccountUpdate::Undelegated { record, slot } => {
+//!         magicblock_sync::AccountUpdate::Undelegated { record, slot, .. } => {
 //!             println!("Undelegation at slot {}", slot);
 //!         }
-//!         dlp_sync::AccountUpdate::SyncTerminated => break,
+//!         magicblock_sync::AccountUpdate::SyncTerminated(reason) => {
+//!             println!("Sync terminated: {:?}", reason);
+//!             break;
+//!         }
+//!         _ => {}
 //!     }
 //! }
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # Firehose mode
+//!
+//! Consumers interested in every delegation event of the program can skip
+//! subscriptions altogether with [`DlpSyncerBuilder::firehose`]:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), magicblock_sync::DlpSyncError> {
+//! let channels = magicblock_sync::DlpSyncer::builder()
+//!     .endpoint("http://localhost:8000")
+//!     .api_key("your-api-key")
+//!     .firehose(true)
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
 
+#[cfg(feature = "serde")]
+mod base58;
+mod builder;
 mod channels;
+mod config;
+mod dedup;
+mod guard;
+mod handle;
+mod instruction;
+#[cfg(any(test, feature = "test-util"))]
+mod local;
+mod metrics;
+mod record;
+#[cfg(feature = "solana-sdk")]
+mod solana;
 mod syncer;
 mod types;
+#[cfg(feature = "cli")]
+mod watch;
 
-pub use channels::{DlpSyncChannelsInit, DlpSyncChannelsRequester};
+pub use builder::DlpSyncerBuilder;
+pub use channels::{DlpSyncChannelsBroadcast, DlpSyncChannelsInit, DlpSyncChannelsRequester};
+pub use config::{BackoffConfig, Backpressure, ConfigSnapshot, DlpSyncConfig};
+pub use guard::SubscriptionGuard;
+pub use handle::DlpSyncHandle;
+pub use instruction::DlpInstruction;
+#[cfg(any(test, feature = "test-util"))]
+pub use local::LocalStream;
+pub use metrics::SyncMetrics;
+pub use record::{derive_delegation_record, DelegationRecord, RecordField, RecordParseError};
+#[cfg(feature = "solana-sdk")]
+pub use solana::{from_solana_pubkey, to_solana_pubkey};
 pub use syncer::DlpSyncer;
-pub use types::{AccountUpdate, DlpSyncError, Pubkey, Slot};
+pub use types::{
+    parse_program_id, AccountUpdate, Commitment, ConnStatus, DlpSyncError, HealthStatus, Pubkey,
+    RequesterError, Signature, Slot, SlotStatus, SubscribeResult, TerminationReason,
+    UndelegationSource,
+};
+#[cfg(feature = "cli")]
+pub use watch::watch;
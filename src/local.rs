@@ -0,0 +1,118 @@
+use futures::channel::mpsc::{self, UnboundedSender};
+use helius_laserstream::{
+    grpc::{
+        subscribe_update::UpdateOneof, SlotStatus as GrpcSlotStatus, SubscribeUpdate,
+        SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateSlot,
+    },
+    LaserstreamError,
+};
+
+use crate::syncer::{LaserStream, CLOSES_FILTER, DELEGATIONS_FILTER};
+use crate::types::{Commitment, Pubkey, PubkeyDisplay, Slot};
+
+/// In-memory update stream driving a syncer started with
+/// [`DlpSyncer::start_local`](crate::DlpSyncer::start_local).
+///
+/// Updates are handled in the order they are pushed, exactly as if they came
+/// from a Laserstream. Dropping the stream ends it, which terminates the
+/// syncer with [`TerminationReason::StreamEnded`](crate::TerminationReason::StreamEnded).
+#[derive(Debug, Clone)]
+pub struct LocalStream {
+    tx: UnboundedSender<Result<SubscribeUpdate, LaserstreamError>>,
+}
+
+impl LocalStream {
+    /// Creates a local stream and the receiving end handed to the syncer.
+    pub(crate) fn new() -> (Self, LaserStream) {
+        let (tx, rx) = mpsc::unbounded();
+        (Self { tx }, Box::pin(rx))
+    }
+
+    /// Pushes a raw update.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the syncer has terminated.
+    pub fn push(&self, update: SubscribeUpdate) -> bool {
+        self.tx.unbounded_send(Ok(update)).is_ok()
+    }
+
+    /// Pushes a stream error, as reported before a reconnect.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the syncer has terminated.
+    pub fn push_error(&self, error: LaserstreamError) -> bool {
+        self.tx.unbounded_send(Err(error)).is_ok()
+    }
+
+    /// Pushes a slot reaching the given commitment.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the syncer has terminated.
+    pub fn push_slot(&self, slot: Slot, commitment: Commitment) -> bool {
+        let status = match commitment {
+            Commitment::Processed => GrpcSlotStatus::SlotProcessed,
+            Commitment::Confirmed => GrpcSlotStatus::SlotConfirmed,
+            Commitment::Finalized => GrpcSlotStatus::SlotFinalized,
+        };
+        self.push(SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                status: status as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        })
+    }
+
+    /// Pushes an update of a delegation record account owned by `program`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the syncer has terminated.
+    pub fn push_record(&self, program: Pubkey, record: Pubkey, data: Vec<u8>, slot: Slot) -> bool {
+        let filter = format!("{DELEGATIONS_FILTER}:{}", PubkeyDisplay(&program));
+        self.push_account(filter, record, program, 1, data, slot)
+    }
+
+    /// Pushes the closing of a delegation record account, reported as an undelegation.
+    ///
+    /// Like a Laserstream, the update only matches the filter on the tracked
+    /// record addresses, as closed accounts are handed back to the system program.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the syncer has terminated.
+    pub fn push_close(&self, record: Pubkey, slot: Slot) -> bool {
+        self.push_account(CLOSES_FILTER.into(), record, [0; 32], 0, Vec::new(), slot)
+    }
+
+    /// Pushes an account update matching the given filter.
+    fn push_account(
+        &self,
+        filter: String,
+        record: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        slot: Slot,
+    ) -> bool {
+        self.push(SubscribeUpdate {
+            filters: vec![filter],
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: record.to_vec(),
+                    lamports,
+                    owner: owner.to_vec(),
+                    data,
+                    ..Default::default()
+                }),
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::types::Slot;
+
+/// Counters and gauges of a running syncer, for external exporters.
+///
+/// Obtained via [`DlpSyncChannelsRequester::metrics`](crate::DlpSyncChannelsRequester::metrics).
+/// Values are updated by the syncer as it runs and can be read at any time
+/// without a round-trip; reads are not synchronized with each other.
+#[derive(Debug, Default)]
+pub struct SyncMetrics {
+    accounts_received: AtomicU64,
+    undelegations_detected: AtomicU64,
+    updates_dropped: AtomicU64,
+    reconnects: AtomicU64,
+    slot_gaps: AtomicU64,
+    callback_panics: AtomicU64,
+    subscriptions_rejected: AtomicU64,
+    current_slot: AtomicU64,
+    active_subscriptions: AtomicU64,
+    pending_updates: AtomicU64,
+}
+
+impl SyncMetrics {
+    /// Delegation record account updates received from the stream.
+    pub fn accounts_received(&self) -> u64 {
+        self.accounts_received.load(Ordering::Relaxed)
+    }
+
+    /// Undelegations detected, including ones still held for commitment.
+    pub fn undelegations_detected(&self) -> u64 {
+        self.undelegations_detected.load(Ordering::Relaxed)
+    }
+
+    /// Updates dropped because the consumer fell behind or went away.
+    pub fn updates_dropped(&self) -> u64 {
+        self.updates_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Reconnection attempts, after stream errors or stalls.
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Forward slot jumps larger than the configured gap threshold.
+    pub fn slot_gaps(&self) -> u64 {
+        self.slot_gaps.load(Ordering::Relaxed)
+    }
+
+    /// Panics caught in user-supplied callbacks.
+    pub fn callback_panics(&self) -> u64 {
+        self.callback_panics.load(Ordering::Relaxed)
+    }
+
+    /// Subscribe requests rejected because of the subscription limit.
+    pub fn subscriptions_rejected(&self) -> u64 {
+        self.subscriptions_rejected.load(Ordering::Relaxed)
+    }
+
+    /// The most recent slot observed.
+    pub fn current_slot(&self) -> Slot {
+        self.current_slot.load(Ordering::Relaxed)
+    }
+
+    /// Number of tracked delegation records.
+    pub fn active_subscriptions(&self) -> u64 {
+        self.active_subscriptions.load(Ordering::Relaxed)
+    }
+
+    /// Number of updates waiting for the consumer, as of the last emitted update.
+    pub fn pending_updates(&self) -> u64 {
+        self.pending_updates.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn inc_accounts_received(&self) {
+        self.accounts_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_undelegations_detected(&self) {
+        self.undelegations_detected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_updates_dropped(&self) {
+        self.updates_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_reconnects(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_slot_gaps(&self) {
+        self.slot_gaps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_callback_panics(&self) {
+        self.callback_panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_subscriptions_rejected(&self) {
+        self.subscriptions_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_current_slot(&self, slot: Slot) {
+        self.current_slot.store(slot, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_active_subscriptions(&self, count: usize) {
+        self.active_subscriptions
+            .store(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_pending_updates(&self, count: usize) {
+        self.pending_updates.store(count as u64, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,298 @@
+use std::{fmt, ops::Range};
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use sha2::{Digest, Sha256};
+
+use crate::types::{Pubkey, PubkeyDisplay, Slot};
+
+/// Size of a delegation record account in bytes.
+pub(crate) const DELEGATION_RECORD_SIZE: u64 = 96;
+
+/// Seed prefix of delegation record addresses.
+const DELEGATION_RECORD_SEED: &[u8] = b"delegation";
+
+/// Marker appended to the seeds when hashing a program derived address.
+const PDA_MARKER: &[u8] = b"ProgramDerivedAddress";
+
+/// Derives the delegation record address of a delegated account.
+///
+/// The record is the program derived address of the seeds
+/// `["delegation", delegated_account]` under the delegation program, using
+/// the highest bump seed that yields an address off the ed25519 curve, as
+/// `Pubkey::find_program_address` does.
+///
+/// # Arguments
+///
+/// * `delegated_account` - The pubkey of the delegated account.
+/// * `program` - The delegation program the account is delegated through.
+pub fn derive_delegation_record(delegated_account: &Pubkey, program: &Pubkey) -> Pubkey {
+    (0..=u8::MAX)
+        .rev()
+        .find_map(|bump| {
+            let address: Pubkey = Sha256::new()
+                .chain_update(DELEGATION_RECORD_SEED)
+                .chain_update(delegated_account)
+                .chain_update([bump])
+                .chain_update(program)
+                .chain_update(PDA_MARKER)
+                .finalize()
+                .into();
+            // Valid curve points have a private key, so cannot be program derived
+            CompressedEdwardsY(address)
+                .decompress()
+                .is_none()
+                .then_some(address)
+        })
+        // Every bump landing on the curve has a negligible probability
+        .expect("no bump seed yields a program derived address")
+}
+
+/// A field of the delegation record account layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecordField {
+    /// The validator authority the account is delegated to.
+    Authority,
+    /// The original owner program of the delegated account.
+    Owner,
+    /// The slot at which the account was delegated.
+    DelegationSlot,
+    /// The lamports of the delegated account at delegation time.
+    Lamports,
+    /// The commit frequency, in milliseconds.
+    CommitFrequencyMs,
+}
+
+impl RecordField {
+    /// All fields, in layout order.
+    pub const ALL: [Self; 5] = [
+        Self::Authority,
+        Self::Owner,
+        Self::DelegationSlot,
+        Self::Lamports,
+        Self::CommitFrequencyMs,
+    ];
+
+    /// Byte range of the field within the record data, after the 8-byte discriminator.
+    pub fn range(self) -> Range<usize> {
+        match self {
+            Self::Authority => 8..40,
+            Self::Owner => 40..72,
+            Self::DelegationSlot => 72..80,
+            Self::Lamports => 80..88,
+            Self::CommitFrequencyMs => 88..96,
+        }
+    }
+}
+
+/// Lists the fields that differ between two record payloads, with their new bytes.
+///
+/// Returns `None` if either payload does not match the record layout size.
+pub(crate) fn diff_fields(previous: &[u8], current: &[u8]) -> Option<Vec<(RecordField, Vec<u8>)>> {
+    let size = DELEGATION_RECORD_SIZE as usize;
+    if previous.len() != size || current.len() != size {
+        return None;
+    }
+
+    let changed = RecordField::ALL
+        .into_iter()
+        .filter(|field| previous[field.range()] != current[field.range()])
+        .map(|field| (field, current[field.range()].to_vec()))
+        .collect();
+    Some(changed)
+}
+
+/// A decoded delegation record account.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DelegationRecord {
+    /// The 8-byte account discriminator.
+    pub discriminator: [u8; 8],
+    /// The validator authority the account is delegated to.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+    pub authority: Pubkey,
+    /// The original owner program of the delegated account.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+    pub owner: Pubkey,
+    /// The slot at which the account was delegated.
+    pub delegation_slot: Slot,
+    /// The lamports of the delegated account at delegation time.
+    pub lamports: u64,
+    /// The commit frequency, in milliseconds.
+    pub commit_frequency_ms: u64,
+}
+
+impl DelegationRecord {
+    /// Decodes a delegation record from raw account data.
+    ///
+    /// Every byte of the layout is retained; see [`to_bytes`](Self::to_bytes).
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, RecordParseError> {
+        let expected = DELEGATION_RECORD_SIZE as usize;
+        if data.len() != expected {
+            return Err(RecordParseError::InvalidSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        // lengths are checked above, so the conversions below cannot fail
+        let pubkey = |field: RecordField| -> Pubkey { data[field.range()].try_into().unwrap() };
+        let le_u64 =
+            |field: RecordField| u64::from_le_bytes(data[field.range()].try_into().unwrap());
+
+        Ok(Self {
+            discriminator: data[..8].try_into().unwrap(),
+            authority: pubkey(RecordField::Authority),
+            owner: pubkey(RecordField::Owner),
+            delegation_slot: le_u64(RecordField::DelegationSlot),
+            lamports: le_u64(RecordField::Lamports),
+            commit_frequency_ms: le_u64(RecordField::CommitFrequencyMs),
+        })
+    }
+
+    /// Encodes the record back into raw account data.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(DELEGATION_RECORD_SIZE as usize);
+        data.extend_from_slice(&self.discriminator);
+        data.extend_from_slice(&self.authority);
+        data.extend_from_slice(&self.owner);
+        data.extend_from_slice(&self.delegation_slot.to_le_bytes());
+        data.extend_from_slice(&self.lamports.to_le_bytes());
+        data.extend_from_slice(&self.commit_frequency_ms.to_le_bytes());
+        data
+    }
+}
+
+impl fmt::Debug for DelegationRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelegationRecord")
+            .field("discriminator", &self.discriminator)
+            .field("authority", &PubkeyDisplay(&self.authority))
+            .field("owner", &PubkeyDisplay(&self.owner))
+            .field("delegation_slot", &self.delegation_slot)
+            .field("lamports", &self.lamports)
+            .field("commit_frequency_ms", &self.commit_frequency_ms)
+            .finish()
+    }
+}
+
+/// Errors that can occur when decoding a [`DelegationRecord`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecordParseError {
+    /// The account data does not match the record layout size.
+    InvalidSize {
+        /// The record layout size, in bytes.
+        expected: usize,
+        /// The size of the given data, in bytes.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for RecordParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize { expected, actual } => write!(
+                f,
+                "invalid delegation record size: expected {expected} bytes, got {actual}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A record with distinct values in every field.
+    fn record() -> DelegationRecord {
+        DelegationRecord {
+            discriminator: [9; 8],
+            authority: [1; 32],
+            owner: [2; 32],
+            delegation_slot: 3,
+            lamports: 4,
+            commit_frequency_ms: 5,
+        }
+    }
+
+    #[test]
+    fn derives_known_delegation_record() {
+        let program = bs58::decode("DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh")
+            .into_vec()
+            .unwrap();
+        // The first bump candidate lands on the curve for this account
+        let record = derive_delegation_record(&[1; 32], &program.try_into().unwrap());
+        assert_eq!(
+            PubkeyDisplay(&record).to_string(),
+            "9ePbKP6631ii3jkPpiKD9CHgh7fgMhYbi1a2VaV5MXNr"
+        );
+    }
+
+    #[test]
+    fn decodes_fields_at_their_offsets() {
+        let data = record().to_bytes();
+        assert_eq!(data.len(), DELEGATION_RECORD_SIZE as usize);
+        assert_eq!(
+            data[RecordField::DelegationSlot.range()],
+            3u64.to_le_bytes()
+        );
+        assert_eq!(DelegationRecord::try_from_bytes(&data), Ok(record()));
+    }
+
+    #[test]
+    fn rejects_records_of_the_wrong_size() {
+        assert_eq!(
+            DelegationRecord::try_from_bytes(&[0; 95]),
+            Err(RecordParseError::InvalidSize {
+                expected: 96,
+                actual: 95
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips_with_base58_keys() {
+        let json = serde_json::to_string(&record()).unwrap();
+        assert!(json.contains(&format!("\"{}\"", PubkeyDisplay(&[1; 32]))));
+        assert_eq!(
+            serde_json::from_str::<DelegationRecord>(&json).unwrap(),
+            record()
+        );
+    }
+
+    #[test]
+    fn diff_reports_changed_fields_only() {
+        let previous = record();
+        let current = DelegationRecord {
+            owner: [6; 32],
+            lamports: 7,
+            ..previous
+        };
+
+        let changed = diff_fields(&previous.to_bytes(), &current.to_bytes()).unwrap();
+        assert_eq!(
+            changed,
+            vec![
+                (RecordField::Owner, vec![6; 32]),
+                (RecordField::Lamports, 7u64.to_le_bytes().to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_identical_records_is_empty() {
+        let data = record().to_bytes();
+        assert_eq!(diff_fields(&data, &data), Some(Vec::new()));
+    }
+
+    #[test]
+    fn diff_requires_the_record_layout() {
+        let data = record().to_bytes();
+        assert_eq!(diff_fields(&data, &data[..95]), None);
+        assert_eq!(diff_fields(&[], &data), None);
+    }
+}
@@ -0,0 +1,35 @@
+use solana_pubkey::Pubkey as SolanaPubkey;
+
+use crate::types::Pubkey;
+
+/// Converts a `solana_sdk` pubkey into the crate's [`Pubkey`].
+///
+/// `solana_sdk::pubkey::Pubkey` re-exports `solana_pubkey::Pubkey`, which is
+/// what this takes, so no full `solana-sdk` dependency is pulled in.
+pub fn from_solana_pubkey(pubkey: &SolanaPubkey) -> Pubkey {
+    pubkey.to_bytes()
+}
+
+/// Converts a [`Pubkey`] into a `solana_sdk` pubkey.
+pub fn to_solana_pubkey(pubkey: &Pubkey) -> SolanaPubkey {
+    SolanaPubkey::new_from_array(*pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Delegation program id, as a well-known base58 key.
+    const KNOWN: &str = "DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh";
+
+    #[test]
+    fn round_trips_a_known_key() {
+        let bytes: Pubkey = bs58::decode(KNOWN).into_vec().unwrap().try_into().unwrap();
+        let solana: SolanaPubkey = KNOWN.parse().unwrap();
+
+        assert_eq!(to_solana_pubkey(&bytes), solana);
+        assert_eq!(from_solana_pubkey(&solana), bytes);
+        assert_eq!(from_solana_pubkey(&to_solana_pubkey(&bytes)), bytes);
+        assert_eq!(to_solana_pubkey(&bytes).to_string(), KNOWN);
+    }
+}
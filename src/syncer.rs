@@ -1,77 +1,199 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+    hash::{Hash, Hasher},
     pin::Pin,
+    sync::Arc,
     time::Duration,
 };
 
 use futures::StreamExt;
 use helius_laserstream::{
-    client,
+    client::{self, StreamHandle},
     grpc::{
         subscribe_request_filter_accounts_filter::Filter, subscribe_update::UpdateOneof,
-        SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
-        SubscribeRequestFilterTransactions, SubscribeRequestPing, SubscribeUpdate,
-        SubscribeUpdateAccount, SubscribeUpdateTransaction,
+        CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts,
+        SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterTransactions, SubscribeUpdate,
+        SubscribeUpdateAccount, SubscribeUpdateSlot, SubscribeUpdateTransaction,
     },
-    solana::storage::confirmed_block::CompiledInstruction,
-    LaserstreamConfig, LaserstreamError,
+    ChannelOptions, LaserstreamConfig, LaserstreamError,
 };
 use tokio::{
-    sync::mpsc::{self, Receiver, Sender},
+    sync::{
+        mpsc::{self, error::TrySendError, Receiver, Sender},
+        watch,
+    },
     time,
 };
+use tracing::Instrument;
 
-use crate::channels::DlpSyncChannelsInit;
-use crate::types::{AccountUpdate, DlpSyncError, Pubkey, Slot};
+use crate::builder::DlpSyncerBuilder;
+use crate::channels::{DlpSyncChannelsBroadcast, DlpSyncChannelsInit, DlpSyncChannelsRequester};
+use crate::config::{env_var, redact_endpoint, Backpressure, ConfigSnapshot, DlpSyncConfig};
+use crate::dedup::{RecentMap, RecentSet};
+use crate::handle::DlpSyncHandle;
+use crate::instruction::DlpInstruction;
+#[cfg(any(test, feature = "test-util"))]
+use crate::local::LocalStream;
+use crate::metrics::SyncMetrics;
+use crate::record::{diff_fields, DelegationRecord};
+use crate::types::{
+    AccountUpdate, Commitment, ConnStatus, DlpSyncError, HealthStatus, Pubkey, PubkeyDisplay,
+    RequesterError, Signature, Slot, SlotStatus, SubscribeResult, TerminationReason,
+    UndelegationSource,
+};
 
 /// Size of a Solana public key in bytes.
 const PUBKEY_LEN: usize = 32;
 
-/// Delegation program address.
-const DELEGATION_PROGRAM: &str = "DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh";
-
-/// Delegation program pubkey in bytes.
-const DELEGATION_PROGRAM_PUBKEY: &Pubkey = &[
+/// Delegation program pubkey in bytes (`DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh`).
+pub(crate) const DELEGATION_PROGRAM_PUBKEY: Pubkey = [
     181, 183, 0, 225, 242, 87, 58, 192, 204, 6, 34, 1, 52, 74, 207, 151, 184, 53, 6, 235, 140, 229,
     25, 152, 204, 98, 126, 24, 147, 128, 167, 62,
 ];
 
-/// Size of a delegation record account in bytes.
-const DELEGATION_RECORD_SIZE: u64 = 96;
+/// Default index of the delegation record account in undelegate instruction accounts.
+pub(crate) const DELEGATION_RECORD_ACCOUNT_INDEX: usize = 6;
+
+/// Index of the delegated account in delegate instruction accounts.
+const DELEGATE_ACCOUNT_INDEX: usize = 1;
+
+/// Index of the delegated account's owner program in delegate instruction accounts.
+const DELEGATE_OWNER_PROGRAM_INDEX: usize = 2;
+
+/// Index of the delegation record account in delegate instruction accounts.
+const DELEGATE_RECORD_ACCOUNT_INDEX: usize = 4;
+
+/// Label prefix of the built-in delegation record account filters, one per program.
+pub(crate) const DELEGATIONS_FILTER: &str = "delegations";
+
+/// Label of the built-in filter on the tracked record addresses alone.
+///
+/// Closed records are handed back to the system program with no data, so
+/// only a filter without owner and size constraints delivers their closing.
+pub(crate) const CLOSES_FILTER: &str = "closes";
+
+/// Label of the built-in undelegation transaction filter.
+const UNDELEGATIONS_FILTER: &str = "undelegations";
+
+/// Default maximum pending subscription/unsubscription requests.
+pub(crate) const MAX_PENDING_REQUESTS: usize = 256;
+
+/// Default maximum pending account/transaction updates.
+pub(crate) const MAX_PENDING_UPDATES: usize = 8192;
+
+/// Default maximum number of tracked delegation records.
+pub(crate) const MAX_SUBSCRIPTIONS: usize = 100_000;
+
+/// Number of recent undelegations remembered for deduplication.
+const UNDELEGATION_DEDUP_CAPACITY: usize = 1024;
+
+/// Number of recent slots remembered as having reached the undelegation commitment.
+const COMMITTED_SLOTS_CAPACITY: usize = 1024;
+
+/// Default maximum reconnection attempts to the Laserstream.
+pub(crate) const MAX_RECONNECT_ATTEMPTS: u32 = 16;
 
-/// Instruction discriminator for undelegate operations.
-const UNDELEGATE_DISCRIMINATOR: u8 = 3;
+/// Default time to wait for the first update after connecting.
+pub(crate) const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Length of an instruction discriminator (Anchor programs).
-const DISCRIMINATOR_LEN: usize = 8;
+/// Delay before pushing a filter update, so bursts of subscription changes coalesce.
+const FILTER_UPDATE_DEBOUNCE: Duration = Duration::from_millis(50);
 
-/// Index of the delegation record account in undelegate instruction accounts.
-const DELEGATION_RECORD_ACCOUNT_INDEX: usize = 6;
+/// Default upper bound on a subscribe round-trip through the request channel.
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Maximum pending subscription/unsubscription requests.
-const MAX_PENDING_REQUESTS: usize = 256;
+/// Default maximum number of filter updates pushed per second.
+pub(crate) const FILTER_UPDATE_RATE: u32 = 10;
 
-/// Maximum pending account/transaction updates.
-const MAX_PENDING_UPDATES: usize = 8192;
+/// Fraction of the update channel in use above which a lagging consumer is reported.
+const UPDATE_CHANNEL_WARN_RATIO: f64 = 0.8;
 
-/// Maximum reconnection attempts to the Laserstream.
-const MAX_RECONNECT_ATTEMPTS: u32 = 16;
+/// Default time without any stream item after which the stream is considered stalled.
+pub(crate) const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default slot jump above which a gap in the stream is reported.
+pub(crate) const SLOT_GAP_THRESHOLD: u64 = 32;
+
+/// Default delay before the first stall-triggered reconnect.
+pub(crate) const RECONNECT_BACKOFF_BASE: Duration = Duration::from_millis(500);
+
+/// Default upper bound on the delay between stall-triggered reconnects.
+pub(crate) const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
 
 /// Stream type alias for Laserstream updates.
-type LaserStream =
+pub(crate) type LaserStream =
     Pin<Box<dyn futures::Stream<Item = Result<SubscribeUpdate, LaserstreamError>> + Send>>;
 
+/// Channel answering a subscribe request.
+pub(crate) type SubscribeResponder =
+    tokio::sync::oneshot::Sender<Result<SubscribeResult, RequesterError>>;
+
+/// Every known delegated record with its data and the slot it was observed at.
+pub(crate) type RecordSnapshot = Vec<(Pubkey, Vec<u8>, Slot)>;
+
 /// Internal message types for sync requests.
 pub(crate) enum SyncRequest {
     /// Subscribe to updates for a delegation record.
     Subscribe {
         /// The delegation record pubkey.
         record: Pubkey,
-        /// Channel to send the current slot back to the requester.
-        slot_tx: tokio::sync::oneshot::Sender<Slot>,
+        /// Commitment of the slot to report, or `None` for the latest observed.
+        commitment: Option<Commitment>,
+        /// Channel to send the current slot, or the rejection, back to the requester.
+        slot_tx: SubscribeResponder,
+    },
+    /// Subscribe to updates for several delegation records at once.
+    SubscribeMany {
+        /// The delegation record pubkeys.
+        records: Vec<Pubkey>,
+        /// Channel to send the current slot, or the rejection, back to the requester.
+        slot_tx: SubscribeResponder,
     },
     /// Unsubscribe from a delegation record.
     Unsubscribe(Pubkey),
+    /// Unsubscribe from every delegation record.
+    UnsubscribeAll,
+    /// Report a snapshot of the subscription set.
+    ListSubscriptions {
+        /// Channel to send the tracked records back to the requester.
+        tx: tokio::sync::oneshot::Sender<Vec<Pubkey>>,
+    },
+    /// Report the current state of a delegation record.
+    GetState {
+        /// The delegation record to look up.
+        record: Pubkey,
+        /// Channel to send the record data and its slot back, `None` if unknown.
+        tx: tokio::sync::oneshot::Sender<Option<(Vec<u8>, Slot)>>,
+    },
+    /// Report the current state of every known delegated record.
+    Snapshot {
+        /// Channel to send the latest observed slot and the records back.
+        tx: tokio::sync::oneshot::Sender<(Slot, RecordSnapshot)>,
+    },
+    /// Report the latest observed slot.
+    CurrentSlot {
+        /// Commitment of the slot to report, or `None` for the latest observed.
+        commitment: Option<Commitment>,
+        /// Channel to send the current slot back to the requester.
+        slot_tx: tokio::sync::oneshot::Sender<Slot>,
+    },
+    /// Drop all updates below the given slot.
+    SetMinSlot(Slot),
+    /// Report the syncer's health.
+    Health(tokio::sync::oneshot::Sender<HealthStatus>),
+    /// Report the time elapsed since the stream last produced an item.
+    LastActivity(tokio::sync::oneshot::Sender<Duration>),
+    /// Report the syncer's effective configuration.
+    ConfigSnapshot(tokio::sync::oneshot::Sender<ConfigSnapshot>),
+    /// Replace the update channel, handing back the new receiver.
+    ReplaceUpdates(tokio::sync::oneshot::Sender<Receiver<AccountUpdate>>),
+    /// Stop the syncer gracefully.
+    Shutdown {
+        /// Channel to send the last processed slot back once queued updates
+        /// are delivered, if the requester waits for it.
+        slot_tx: Option<tokio::sync::oneshot::Sender<Slot>>,
+    },
 }
 
 /// The main DLP synchronization service.
@@ -83,12 +205,120 @@ pub struct DlpSyncer {
     subscriptions: HashSet<Pubkey>,
     /// The Laserstream update stream.
     stream: LaserStream,
+    /// Handle for rewriting the subscription on the live stream.
+    ///
+    /// `None` for streams not backed by a Laserstream connection.
+    handle: Option<StreamHandle>,
+    /// When to push the next server-side filter update, if one is pending.
+    filter_deadline: Option<time::Instant>,
+    /// When the last server-side filter update was pushed.
+    last_filter_push: Option<time::Instant>,
+    /// Last activity of each subscribed record, when subscriptions expire.
+    last_activity: HashMap<Pubkey, time::Instant>,
+    /// When the next subscription may expire, if any is tracked.
+    expiry_deadline: Option<time::Instant>,
     /// Receiver for incoming subscription requests.
     requests: Receiver<SyncRequest>,
     /// Sender for broadcasting updates to subscribers.
     updates: Sender<AccountUpdate>,
-    /// Current slot number.
+    /// Updates waiting for channel capacity under [`Backpressure::DropOldest`]
+    /// and [`Backpressure::Block`].
+    outbox: VecDeque<AccountUpdate>,
+    /// Highest slot observed at any commitment level.
     slot: Slot,
+    /// Latest slot per commitment level, indexed by [`Commitment`].
+    commitment_slots: [Slot; 3],
+    /// First slot observed during this session.
+    first_slot: Option<Slot>,
+    /// Whether the stream is currently connected.
+    connected: watch::Sender<bool>,
+    /// Updates below this slot are dropped.
+    min_slot: Slot,
+    /// Runtime configuration.
+    config: DlpSyncConfig,
+    /// Redacted Laserstream endpoint currently in use, for introspection.
+    endpoint: String,
+    /// All Laserstream endpoints, in order of preference.
+    endpoints: Vec<String>,
+    /// Index of the endpoint currently in use.
+    endpoint_index: usize,
+    /// Recently emitted undelegations, to suppress exact repeats.
+    undelegations: RecentSet<(Pubkey, Slot, Option<UndelegationSource>, bool)>,
+    /// Undelegations waiting for their slot to reach the configured commitment.
+    pending_undelegations: BTreeMap<Slot, Vec<AccountUpdate>>,
+    /// Recent slots that reached the configured undelegation commitment.
+    committed_slots: RecentSet<Slot>,
+    /// Updates waiting for their slot to be confirmed, when delivering in slot order.
+    reorder: BTreeMap<Slot, Vec<AccountUpdate>>,
+    /// Highest confirmed slot, up to which updates are delivered as they come.
+    ordered_through: Slot,
+    /// Signatures of recent undelegation transactions, if signature dedup is enabled.
+    seen_signatures: Option<RecentSet<Signature>>,
+    /// Last seen record data per record, if delta updates are enabled.
+    last_records: HashMap<Pubkey, Vec<u8>>,
+    /// Program each subscribed record was last seen owned by, to attribute closes.
+    record_programs: HashMap<Pubkey, Pubkey>,
+    /// Latest data and slot of each delegated record in the subscription set.
+    states: HashMap<Pubkey, (Vec<u8>, Slot)>,
+    /// Last emitted (slot, data hash) per record, if account dedup is enabled.
+    seen_accounts: Option<RecentMap<Pubkey, (Slot, u64)>>,
+    /// Whether a graceful shutdown was requested.
+    shutdown: bool,
+    /// Requesters waiting for the final slot of a graceful shutdown.
+    shutdown_acks: Vec<tokio::sync::oneshot::Sender<Slot>>,
+    /// Whether `SyncTerminated` has been sent.
+    terminated: bool,
+    /// The last stream error since the last successful update, reported if the stream gives up.
+    last_error: Option<String>,
+    /// Consecutive stream errors since the last successful update.
+    reconnect_attempt: u32,
+    /// Connection parameters, kept to re-establish a stalled stream.
+    laserstream: LaserstreamConfig,
+    /// When the stream last yielded an item.
+    last_item: time::Instant,
+    /// When to re-establish a stalled stream, if a reconnect is pending.
+    reconnect_deadline: Option<time::Instant>,
+    /// When to emit the next heartbeat, if enabled.
+    heartbeat_deadline: Option<time::Instant>,
+    /// End of the warm-up period, if still warming up.
+    warmup_deadline: Option<time::Instant>,
+    /// Subscriptions received during warm-up, applied once it ends.
+    warmup_subscriptions: Vec<(Vec<Pubkey>, Option<Commitment>, SubscribeResponder)>,
+    /// End of the bootstrap window, if still bootstrapping.
+    bootstrap_deadline: Option<time::Instant>,
+    /// Latest delegation per record seen during the bootstrap window.
+    snapshot: Option<HashMap<Pubkey, AccountUpdate>>,
+    /// Whether the update channel is above the warning ratio.
+    channel_congested: bool,
+    /// Counters and gauges shared with the requesters.
+    metrics: Arc<SyncMetrics>,
+}
+
+impl fmt::Debug for DlpSyncer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DlpSyncer")
+            .field("subscriptions", &self.subscriptions.len())
+            .field("slot", &self.slot)
+            .field("first_slot", &self.first_slot)
+            .field("min_slot", &self.min_slot)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DlpSyncer {
+    /// Attempts to notify subscribers when the task is aborted or panics
+    /// before the run loop could send `SyncTerminated` itself.
+    fn drop(&mut self) {
+        if !self.terminated {
+            self.connected.send_replace(false);
+            let _ = self
+                .updates
+                .try_send(AccountUpdate::SyncTerminated(TerminationReason::Fatal(
+                    "syncer task aborted".into(),
+                )));
+        }
+    }
 }
 
 impl DlpSyncer {
@@ -107,212 +337,2596 @@ impl DlpSyncer {
     /// The service is spawned onto the current tokio runtime and will run
     /// until either the stream disconnects or all channel senders are dropped.
     pub async fn start(endpoint: String, key: String) -> Result<DlpSyncChannelsInit, DlpSyncError> {
-        let config = LaserstreamConfig {
-            api_key: key,
-            endpoint,
-            channel_options: Default::default(),
-            max_reconnect_attempts: Some(MAX_RECONNECT_ATTEMPTS),
-            replay: true,
-        };
+        Self::builder()
+            .endpoint(endpoint)
+            .api_key(key)
+            .build()
+            .await
+    }
+
+    /// Starts a new DLP synchronization service whose updates are broadcast
+    /// to multiple consumers.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The Laserstream gRPC endpoint URL.
+    /// * `key` - The API key for authentication.
+    ///
+    /// # Returns
+    ///
+    /// Returns [`DlpSyncChannelsBroadcast`] containing the request channel and a
+    /// broadcast sender to subscribe consumers from, or a [`DlpSyncError`] if the
+    /// connection fails. See [`DlpSyncChannelsInit::into_broadcast`] for the
+    /// lag and termination semantics.
+    pub async fn start_broadcast(
+        endpoint: String,
+        key: String,
+    ) -> Result<DlpSyncChannelsBroadcast, DlpSyncError> {
+        Self::builder()
+            .endpoint(endpoint)
+            .api_key(key)
+            .build_broadcast()
+            .await
+    }
+
+    /// Starts a new DLP synchronization service that runs a handler on every update.
+    ///
+    /// The handler is driven in its own task until it has seen
+    /// [`AccountUpdate::SyncTerminated`]. It runs synchronously per update, so a
+    /// slow handler lets the update channel fill up, at which point updates
+    /// are dropped according to the [`Backpressure`] policy. A panicking
+    /// handler is logged and skipped for that update rather than stopping
+    /// delivery, but handlers should still be written not to panic.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The Laserstream gRPC endpoint URL.
+    /// * `key` - The API key for authentication.
+    /// * `handler` - The closure invoked with every update.
+    ///
+    /// # Returns
+    ///
+    /// Returns a [`DlpSyncChannelsRequester`] for subscribing to records,
+    /// or a [`DlpSyncError`] if the connection fails.
+    pub async fn start_with_handler<F>(
+        endpoint: String,
+        key: String,
+        handler: F,
+    ) -> Result<DlpSyncChannelsRequester, DlpSyncError>
+    where
+        F: FnMut(AccountUpdate) + Send + 'static,
+    {
+        Self::builder()
+            .endpoint(endpoint)
+            .api_key(key)
+            .build_with_handler(handler)
+            .await
+    }
 
-        let (requests_tx, requests_rx) = mpsc::channel(MAX_PENDING_REQUESTS);
-        let (updates_tx, updates_rx) = mpsc::channel(MAX_PENDING_UPDATES);
+    /// Returns a [`DlpSyncerBuilder`] for configuring the service before starting it.
+    pub fn builder() -> DlpSyncerBuilder {
+        DlpSyncerBuilder::default()
+    }
+
+    /// Starts a new DLP synchronization service with the given configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The Laserstream gRPC endpoint URL.
+    /// * `key` - The API key for authentication.
+    /// * `config` - The runtime configuration, see [`DlpSyncConfig`].
+    ///
+    /// # Returns
+    ///
+    /// Returns [`DlpSyncChannelsInit`] containing both request and update channels,
+    /// or a [`DlpSyncError`] if the connection fails.
+    pub async fn start_with_config(
+        endpoint: String,
+        key: String,
+        config: DlpSyncConfig,
+    ) -> Result<DlpSyncChannelsInit, DlpSyncError> {
+        Self::start_with_endpoints(vec![endpoint], key, config).await
+    }
 
-        let stream = Self::connect(config).await?;
+    /// Starts a new DLP synchronization service configured from environment variables.
+    ///
+    /// Reads the endpoint from `LASERSTREAM_ENDPOINT`, several comma-separated
+    /// ones enabling failover as in [`start_with_endpoints`](Self::start_with_endpoints),
+    /// and the API key from `LASERSTREAM_API_KEY`. The rest of the configuration
+    /// comes from [`DlpSyncConfig::from_env`]. Explicit configuration through
+    /// [`DlpSyncer::builder`] remains the primary way to start the syncer.
+    ///
+    /// # Returns
+    ///
+    /// Returns [`DlpSyncChannelsInit`] containing both request and update channels,
+    /// or a [`DlpSyncError`] if a variable is missing or malformed, or the
+    /// connection fails.
+    pub async fn start_from_env() -> Result<DlpSyncChannelsInit, DlpSyncError> {
+        let endpoints = env_var("LASERSTREAM_ENDPOINT")
+            .ok_or(DlpSyncError::Connection(
+                "missing environment variable: LASERSTREAM_ENDPOINT",
+            ))?
+            .split(',')
+            .map(str::trim)
+            .filter(|endpoint| !endpoint.is_empty())
+            .map(String::from)
+            .collect();
+        let key = env_var("LASERSTREAM_API_KEY").ok_or(DlpSyncError::Connection(
+            "missing environment variable: LASERSTREAM_API_KEY",
+        ))?;
+        let config = DlpSyncConfig::from_env()?;
+        Self::start_with_endpoints(endpoints, key, config).await
+    }
 
-        let syncer = Self {
-            subscriptions: HashSet::new(),
-            stream,
-            requests: requests_rx,
-            updates: updates_tx,
-            slot: 0,
+    /// Starts a new DLP synchronization service that fails over between endpoints.
+    ///
+    /// The endpoints are tried in order at startup, and the first one to pass
+    /// the health check is used. When the stream stalls, the syncer moves on
+    /// to the next endpoint, wrapping around after the last one. Reconnects
+    /// after stream errors are handled by the Laserstream client and stay on
+    /// the current endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoints` - The Laserstream gRPC endpoint URLs, in order of preference.
+    /// * `key` - The API key for authentication, shared by all endpoints.
+    /// * `config` - The runtime configuration, see [`DlpSyncConfig`].
+    ///
+    /// # Returns
+    ///
+    /// Returns [`DlpSyncChannelsInit`] containing both request and update channels,
+    /// or the [`DlpSyncError`] of the last endpoint if none could be connected.
+    pub async fn start_with_endpoints(
+        endpoints: Vec<String>,
+        key: String,
+        config: DlpSyncConfig,
+    ) -> Result<DlpSyncChannelsInit, DlpSyncError> {
+        if config.firehose {
+            tracing::warn!("firehose mode enabled, every delegation event will be emitted");
+        }
+        if config.dry_run {
+            tracing::warn!("dry run enabled, updates will be logged instead of emitted");
+        }
+
+        let span = tracing::info_span!(
+            "dlp_syncer",
+            endpoint = tracing::field::Empty,
+            slot = tracing::field::Empty,
+            subscriptions = 0usize,
+        );
+        let laserstream = Self::laserstream_config(key, &config);
+
+        let (requests_tx, requests_rx) = mpsc::channel(config.request_capacity.max(1));
+        let (updates_tx, updates_rx) = mpsc::channel(config.update_capacity.max(1));
+
+        if config
+            .extra_transaction_filters
+            .contains_key(UNDELEGATIONS_FILTER)
+        {
+            tracing::warn!(
+                label = UNDELEGATIONS_FILTER,
+                "ignoring extra transaction filter with reserved label"
+            );
+        }
+
+        // Only the initial request starts from the configured slot, later
+        // writes would otherwise rewind the stream
+        let mut request = Self::subscribe_request(&config, &HashSet::new());
+        request.from_slot = config.from_slot;
+        let connect = Self::connect_first(
+            &laserstream,
+            &endpoints,
+            request,
+            config.health_check_timeout,
+        )
+        .instrument(tracing::info_span!(parent: &span, "connect"));
+        let (endpoint_index, stream, handle) = match config.start_timeout {
+            Some(limit) => time::timeout(limit, connect)
+                .await
+                .map_err(|_| DlpSyncError::Connection("startup timed out"))??,
+            None => connect.await?,
+        };
+
+        let request_timeout = config.request_timeout;
+        let delegation_programs = config.delegation_programs.clone().into();
+        let mut syncer = Self::from_parts(config, stream, requests_rx, updates_tx);
+        syncer.handle = Some(handle);
+        syncer.laserstream = LaserstreamConfig {
+            endpoint: endpoints[endpoint_index].clone(),
+            ..laserstream
         };
+        syncer.endpoint = redact_endpoint(&syncer.laserstream.endpoint);
+        syncer.endpoints = endpoints;
+        syncer.endpoint_index = endpoint_index;
+        span.record("endpoint", syncer.endpoint.as_str());
+        let connected_rx = syncer.connected.subscribe();
+        let metrics = syncer.metrics.clone();
 
-        tokio::spawn(syncer.run());
+        let task = tokio::spawn(syncer.run().instrument(span));
 
         Ok(crate::channels::DlpSyncChannels {
             requests: requests_tx,
             updates: updates_rx,
+            connected: connected_rx,
+            request_timeout,
+            delegation_programs,
+            metrics,
+            task: Some(DlpSyncHandle { task }),
         })
     }
 
+    /// Starts a DLP synchronization service fed by an in-memory stream
+    /// instead of a Laserstream connection.
+    ///
+    /// Meant for testing consumers deterministically: updates pushed into the
+    /// returned [`LocalStream`] are processed like stream updates, and requests
+    /// behave as usual. Nothing connects, so the stall timeout is disabled and
+    /// subscription changes have no server-side effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The runtime configuration, see [`DlpSyncConfig`].
+    ///
+    /// # Returns
+    ///
+    /// Returns [`DlpSyncChannelsInit`] containing both request and update
+    /// channels, along with the [`LocalStream`] to push updates into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn start_local(mut config: DlpSyncConfig) -> (DlpSyncChannelsInit, LocalStream) {
+        config.stall_timeout = None;
+        let span = tracing::info_span!(
+            "dlp_syncer",
+            endpoint = "local",
+            slot = tracing::field::Empty,
+            subscriptions = 0usize,
+        );
+
+        let (requests_tx, requests_rx) = mpsc::channel(config.request_capacity.max(1));
+        let (updates_tx, updates_rx) = mpsc::channel(config.update_capacity.max(1));
+        let (local, stream) = LocalStream::new();
+
+        let request_timeout = config.request_timeout;
+        let delegation_programs = config.delegation_programs.clone().into();
+        let mut syncer = Self::from_parts(config, stream, requests_rx, updates_tx);
+        syncer.endpoint = "local".into();
+        let connected_rx = syncer.connected.subscribe();
+        let metrics = syncer.metrics.clone();
+
+        let task = tokio::spawn(syncer.run().instrument(span));
+
+        let channels = crate::channels::DlpSyncChannels {
+            requests: requests_tx,
+            updates: updates_rx,
+            connected: connected_rx,
+            request_timeout,
+            delegation_programs,
+            metrics,
+            task: Some(DlpSyncHandle { task }),
+        };
+        (channels, local)
+    }
+
+    /// Assembles a syncer around an already established update stream.
+    ///
+    /// The syncer has no handle to push filter updates with, so any stream of
+    /// [`SubscribeUpdate`]s can drive it, e.g. a hand-built one in tests.
+    /// Callers backed by a Laserstream fill in the connection fields afterwards.
+    pub(crate) fn from_parts(
+        config: DlpSyncConfig,
+        stream: LaserStream,
+        requests: Receiver<SyncRequest>,
+        updates: Sender<AccountUpdate>,
+    ) -> Self {
+        let warming_up = !config.warmup.is_zero();
+        let (connected_tx, _) = watch::channel(!warming_up);
+        let warmup_deadline = warming_up.then(|| time::Instant::now() + config.warmup);
+        let bootstrap_deadline = config
+            .bootstrap_window
+            .map(|window| time::Instant::now() + window);
+        let snapshot = bootstrap_deadline.map(|_| HashMap::new());
+        let seen_signatures = config.signature_dedup_capacity.map(RecentSet::new);
+        let seen_accounts = config.account_dedup_capacity.map(RecentMap::new);
+        let heartbeat_deadline = config
+            .heartbeat_interval
+            .map(|interval| time::Instant::now() + interval);
+
+        Self {
+            subscriptions: HashSet::new(),
+            stream,
+            handle: None,
+            filter_deadline: None,
+            last_filter_push: None,
+            last_activity: HashMap::new(),
+            expiry_deadline: None,
+            requests,
+            updates,
+            outbox: VecDeque::new(),
+            slot: 0,
+            commitment_slots: [0; 3],
+            first_slot: None,
+            connected: connected_tx,
+            min_slot: 0,
+            config,
+            endpoint: String::new(),
+            endpoints: Vec::new(),
+            endpoint_index: 0,
+            undelegations: RecentSet::new(UNDELEGATION_DEDUP_CAPACITY),
+            pending_undelegations: BTreeMap::new(),
+            committed_slots: RecentSet::new(COMMITTED_SLOTS_CAPACITY),
+            reorder: BTreeMap::new(),
+            ordered_through: 0,
+            seen_signatures,
+            last_records: HashMap::new(),
+            record_programs: HashMap::new(),
+            states: HashMap::new(),
+            seen_accounts,
+            shutdown: false,
+            shutdown_acks: Vec::new(),
+            terminated: false,
+            last_error: None,
+            reconnect_attempt: 0,
+            laserstream: LaserstreamConfig::default(),
+            last_item: time::Instant::now(),
+            reconnect_deadline: None,
+            heartbeat_deadline,
+            warmup_deadline,
+            bootstrap_deadline,
+            snapshot,
+            channel_congested: false,
+            metrics: Arc::new(SyncMetrics::default()),
+            warmup_subscriptions: Vec::new(),
+        }
+    }
+
     /// Main event loop for the synchronization service.
     ///
     /// Handles both incoming requests from subscribers and updates from the Laserstream.
     async fn run(mut self) {
-        loop {
+        let reason = loop {
+            if self.shutdown {
+                break TerminationReason::Shutdown;
+            }
+            // Under `Block`, queued updates hold back the stream until delivered
+            let blocked = self.config.backpressure == Backpressure::Block
+                && !self.outbox.is_empty()
+                && !self.updates.is_closed();
+            let stall_deadline = self
+                .config
+                .stall_timeout
+                .filter(|_| !blocked && self.reconnect_deadline.is_none())
+                .map(|t| self.last_item + t);
+
             tokio::select! {
-                Some(update) = self.stream.next() => self.handle_update(update),
-                Some(request) = self.requests.recv() => self.handle_request(request),
-                else => break,
+                update = self.stream.next(), if !blocked => match update {
+                    Some(update) => self.handle_update(update),
+                    // The stream only gives up after exhausting its reconnect attempts
+                    None => break match self.last_error.take() {
+                        Some(error) => TerminationReason::Fatal(error),
+                        None => TerminationReason::StreamEnded,
+                    },
+                },
+                request = self.requests.recv() => match request {
+                    Some(request) => self.handle_request(request),
+                    None => break TerminationReason::ChannelsClosed,
+                },
+                _ = time::sleep_until(self.warmup_deadline.unwrap_or_else(time::Instant::now)),
+                    if self.warmup_deadline.is_some() => self.finish_warmup(),
+                _ = time::sleep_until(self.bootstrap_deadline.unwrap_or_else(time::Instant::now)),
+                    if self.bootstrap_deadline.is_some() => self.finish_bootstrap(),
+                _ = time::sleep_until(self.filter_deadline.unwrap_or_else(time::Instant::now)),
+                    if self.filter_deadline.is_some() => self.push_filter().await,
+                _ = time::sleep_until(stall_deadline.unwrap_or_else(time::Instant::now)),
+                    if stall_deadline.is_some() => self.schedule_reconnect(),
+                _ = time::sleep_until(self.reconnect_deadline.unwrap_or_else(time::Instant::now)),
+                    if self.reconnect_deadline.is_some() => self.reconnect_stalled(),
+                _ = time::sleep_until(self.heartbeat_deadline.unwrap_or_else(time::Instant::now)),
+                    if self.heartbeat_deadline.is_some() => self.send_heartbeat(),
+                _ = time::sleep_until(self.expiry_deadline.unwrap_or_else(time::Instant::now)),
+                    if self.expiry_deadline.is_some() => self.expire_idle(),
+                Ok(permit) = self.updates.clone().reserve_owned(), if !self.outbox.is_empty() => {
+                    if let Some(update) = self.outbox.pop_front() {
+                        permit.send(update);
+                    }
+                }
+            }
+        };
+
+        if matches!(
+            reason,
+            TerminationReason::StreamEnded | TerminationReason::Fatal(_)
+        ) {
+            self.emit(AccountUpdate::ConnectionStatus(ConnStatus::Disconnected));
+        }
+
+        // Deliver whatever is still queued before terminating.
+        for update in std::mem::take(&mut self.outbox) {
+            if self.updates.send(update).await.is_err() {
+                break;
             }
         }
 
+        for ack in std::mem::take(&mut self.shutdown_acks) {
+            let _ = ack.send(self.slot);
+        }
+
         // Notify all subscribers that the sync has terminated.
-        let _ = self.updates.send(AccountUpdate::SyncTerminated).await;
+        self.connected.send_replace(false);
+        tracing::info!(?reason, "sync terminated");
+        let _ = self
+            .updates
+            .send(AccountUpdate::SyncTerminated(reason))
+            .await;
+        self.terminated = true;
     }
 
     /// Handles a subscription or unsubscription request.
     fn handle_request(&mut self, request: SyncRequest) {
         match request {
-            SyncRequest::Subscribe { record, slot_tx } => {
-                self.subscriptions.insert(record);
-                let _ = slot_tx.send(self.slot);
+            SyncRequest::Subscribe {
+                record,
+                commitment,
+                slot_tx,
+            } => self.subscribe_records(vec![record], commitment, slot_tx),
+            SyncRequest::SubscribeMany { records, slot_tx } => {
+                self.subscribe_records(records, None, slot_tx)
+            }
+            SyncRequest::Unsubscribe(record) => self.unsubscribe_record(&record),
+            SyncRequest::UnsubscribeAll => {
+                // Subscriptions buffered during warm-up predate the reset
+                for (_, commitment, slot_tx) in std::mem::take(&mut self.warmup_subscriptions) {
+                    let _ = slot_tx.send(Ok(SubscribeResult {
+                        slot: self.slot_at(commitment),
+                        currently_delegated: false,
+                    }));
+                }
+                self.last_activity.clear();
+                for record in std::mem::take(&mut self.subscriptions) {
+                    self.last_records.remove(&record);
+                    self.record_programs.remove(&record);
+                    self.states.remove(&record);
+                    if let Some(seen) = &mut self.seen_accounts {
+                        seen.remove(&record);
+                    }
+                }
+                tracing::debug!("unsubscribed from all records");
+                self.subscriptions_changed();
+                self.schedule_filter_update();
             }
-            SyncRequest::Unsubscribe(record) => {
-                self.subscriptions.remove(&record);
+            SyncRequest::ListSubscriptions { tx } => {
+                let _ = tx.send(self.subscriptions.iter().copied().collect());
+            }
+            SyncRequest::GetState { record, tx } => {
+                let _ = tx.send(self.states.get(&record).cloned());
+            }
+            SyncRequest::Snapshot { tx } => {
+                let records = self
+                    .states
+                    .iter()
+                    .map(|(record, (data, slot))| (*record, data.clone(), *slot))
+                    .collect();
+                let _ = tx.send((self.slot, records));
+            }
+            SyncRequest::CurrentSlot {
+                commitment,
+                slot_tx,
+            } => {
+                let _ = slot_tx.send(self.slot_at(commitment));
+            }
+            SyncRequest::SetMinSlot(slot) => {
+                self.min_slot = slot;
+            }
+            SyncRequest::Health(tx) => {
+                let _ = tx.send(HealthStatus {
+                    connected: *self.connected.borrow(),
+                    first_slot: self.first_slot,
+                    current_slot: self.slot,
+                    subscriptions: self.subscriptions.len(),
+                    pending_updates: self.pending_updates(),
+                });
+            }
+            SyncRequest::LastActivity(tx) => {
+                let _ = tx.send(self.last_item.elapsed());
+            }
+            SyncRequest::ConfigSnapshot(tx) => {
+                let _ = tx.send(self.config_snapshot());
+            }
+            SyncRequest::Shutdown { slot_tx } => {
+                self.shutdown = true;
+                self.shutdown_acks.extend(slot_tx);
+            }
+            SyncRequest::ReplaceUpdates(tx) => {
+                let (updates_tx, updates_rx) = mpsc::channel(self.config.update_capacity.max(1));
+                if tx.send(updates_rx).is_ok() {
+                    self.updates = updates_tx;
+                }
             }
         }
     }
 
-    /// Handles an update from the Laserstream.
-    fn handle_update(&mut self, result: Result<SubscribeUpdate, LaserstreamError>) {
-        use UpdateOneof::*;
+    /// The latest slot at the given commitment, or the latest observed slot for `None`.
+    fn slot_at(&self, commitment: Option<Commitment>) -> Slot {
+        match commitment {
+            Some(commitment) => self.commitment_slots[commitment as usize],
+            None => self.slot,
+        }
+    }
 
-        let update = match result {
-            Ok(u) => match u.update_oneof {
-                Some(update) => update,
-                None => return,
-            },
-            Err(error) => {
-                tracing::warn!(%error, "error during stream processing");
-                return;
-            }
-        };
+    /// Schedules the replacement of a stream that stopped yielding items
+    /// without erroring, e.g. on a half-open connection, or that can no longer
+    /// be written to, after a backoff.
+    fn schedule_reconnect(&mut self) {
+        self.reconnect_attempt += 1;
+        self.metrics.inc_reconnects();
+        let delay = self.config.backoff.delay(self.reconnect_attempt);
+        tracing::warn!(
+            attempt = self.reconnect_attempt,
+            ?delay,
+            "stream unresponsive, reconnecting"
+        );
+        self.connected.send_replace(false);
+        self.emit(AccountUpdate::ConnectionStatus(ConnStatus::Reconnecting {
+            attempt: self.reconnect_attempt,
+            delay: Some(delay),
+        }));
+        self.reconnect_deadline = Some(time::Instant::now() + delay);
+    }
 
-        match update {
-            Account(acc) => self.handle_account_update(acc),
-            Slot(slot) => self.slot = slot.slot,
-            Transaction(txn) => self.handle_transaction_update(txn),
-            _ => {}
+    /// Replaces a stalled stream with a fresh subscription.
+    fn reconnect_stalled(&mut self) {
+        self.reconnect_deadline = None;
+        // The current endpoint may be the problem, so fail over if possible
+        if self.endpoints.len() > 1 {
+            self.endpoint_index = (self.endpoint_index + 1) % self.endpoints.len();
+            self.laserstream.endpoint = self.endpoints[self.endpoint_index].clone();
+            self.endpoint = redact_endpoint(&self.laserstream.endpoint);
+            tracing::Span::current().record("endpoint", self.endpoint.as_str());
+            tracing::info!(endpoint = %self.endpoint, "failing over to next endpoint");
         }
+
+        let mut request = Self::subscribe_request(&self.config, &self.subscriptions);
+        // Resume from the last seen slot, as the client would on its own reconnects
+        if self.config.replay {
+            request.from_slot = Some(self.slot)
+                .filter(|&slot| slot > 0)
+                .or(self.config.from_slot);
+        }
+
+        let (stream, handle) = client::subscribe(self.laserstream.clone(), request);
+        self.stream = Box::pin(stream);
+        self.handle = Some(handle);
+        self.last_item = time::Instant::now();
     }
 
-    /// Handles an account (delegation record) update.
-    fn handle_account_update(&self, acc: SubscribeUpdateAccount) {
-        let Some(account) = acc.account else { return };
+    /// Resolves the delegation program a record update belongs to.
+    ///
+    /// Live records are owned by their program. Closed records are owned by
+    /// the system program instead, so they are attributed to the program the
+    /// record was last seen under, or to the only configured program.
+    fn record_program(&self, record: &Pubkey, owner: &[u8], closed: bool) -> Option<Pubkey> {
+        let programs = &self.config.delegation_programs;
+        if let Some(program) = programs.iter().find(|p| p[..] == *owner) {
+            return Some(*program);
+        }
+        if !closed {
+            return None;
+        }
+        self.record_programs
+            .get(record)
+            .copied()
+            .or_else(|| (programs.len() == 1).then(|| programs[0]))
+    }
 
-        if account.pubkey.len() != PUBKEY_LEN {
+    /// Schedules a server-side filter update, coalescing with any already pending.
+    ///
+    /// Updates are spaced according to the configured rate limit; changes made
+    /// in the meantime are folded into the pending update.
+    fn schedule_filter_update(&mut self) {
+        if self.config.firehose || self.filter_deadline.is_some() {
             return;
         }
+        let mut deadline = time::Instant::now() + FILTER_UPDATE_DEBOUNCE;
+        if let Some((last, rate)) = self.last_filter_push.zip(self.config.filter_update_rate) {
+            deadline = deadline.max(last + Duration::from_secs(1) / rate.max(1));
+        }
+        self.filter_deadline = Some(deadline);
+    }
 
-        if !self.subscriptions.contains(account.pubkey.as_slice()) {
-            return;
+    /// Pushes the current subscription set to the server as the account filter.
+    async fn push_filter(&mut self) {
+        self.filter_deadline = None;
+        self.last_filter_push = Some(time::Instant::now());
+
+        let request = Self::subscribe_request(&self.config, &self.subscriptions);
+        let Some(handle) = &self.handle else { return };
+        if let Err(error) = handle.write(request).await {
+            tracing::warn!(%error, "failed to update subscription filter");
         }
+    }
 
-        let Ok(record) = Pubkey::try_from(account.pubkey.as_slice()) else {
-            return;
-        };
+    /// Emits a heartbeat update and schedules the next one.
+    fn send_heartbeat(&mut self) {
+        self.heartbeat_deadline = self
+            .config
+            .heartbeat_interval
+            .map(|i| time::Instant::now() + i);
+        self.emit(AccountUpdate::Heartbeat { slot: self.slot });
+    }
 
-        let update = AccountUpdate::Delegated {
-            record,
-            data: account.data,
-            slot: acc.slot,
-        };
+    /// Builds a snapshot of the effective configuration.
+    fn config_snapshot(&self) -> ConfigSnapshot {
+        let config = &self.config;
+        ConfigSnapshot {
+            endpoint: self.endpoint.clone(),
+            program_ids: config
+                .delegation_programs
+                .iter()
+                .map(|program| PubkeyDisplay(program).to_string())
+                .collect(),
+            record_size: config.record_size,
+            request_capacity: config.request_capacity,
+            update_capacity: config.update_capacity,
+            max_subscriptions: config.max_subscriptions,
+            commitment: config.commitment,
+            stall_timeout: config.stall_timeout,
+            heartbeat_interval: config.heartbeat_interval,
+            filter_update_rate: config.filter_update_rate,
+            backoff: config.backoff,
+            request_timeout: config.request_timeout,
+            replay: config.replay,
+            from_slot: config.from_slot,
+            accept_compression: config.accept_compression.clone(),
+            send_compression: config.send_compression,
+            max_reconnect_attempts: config.max_reconnect_attempts,
+            firehose: config.firehose,
+            dry_run: config.dry_run,
+            dual_source_undelegations: config.dual_source_undelegations,
+            undelegation_commitment: config.undelegation_commitment,
+            slot_status_updates: config.slot_status_updates,
+            slot_ordered: config.slot_ordered,
+            slot_gap_threshold: config.slot_gap_threshold,
+            raw_passthrough: config.raw_passthrough,
+            signature_dedup_capacity: config.signature_dedup_capacity,
+            account_dedup_capacity: config.account_dedup_capacity,
+            backpressure: config.backpressure,
+            delta_updates: config.delta_updates,
+            parse_records: config.parse_records,
+            include_failed_undelegations: config.include_failed_undelegations,
+            batch_undelegations: config.batch_undelegations,
+            auto_unsubscribe_on_undelegate: config.auto_unsubscribe_on_undelegate,
+            subscription_ttl: config.subscription_ttl,
+            bootstrap_window: config.bootstrap_window,
+            extra_transaction_filters: config.extra_transaction_filters.keys().cloned().collect(),
+            min_slot: self.min_slot,
+        }
+    }
+
+    /// Number of updates not yet received by the consumer.
+    fn pending_updates(&self) -> usize {
+        self.updates.max_capacity() - self.updates.capacity() + self.outbox.len()
+    }
 
-        if let Err(error) = self.updates.try_send(update) {
-            tracing::error!(%error, "failed to send delegation update");
+    /// Warns once when the update channel fills past the warning ratio, and
+    /// once more when it drains back below half of it.
+    fn check_congestion(&mut self) {
+        self.metrics.set_pending_updates(self.pending_updates());
+        let max = self.updates.max_capacity();
+        let used = (max - self.updates.capacity()) as f64 / max as f64;
+        if !self.channel_congested && used >= UPDATE_CHANNEL_WARN_RATIO {
+            self.channel_congested = true;
+            tracing::warn!(
+                pending = self.pending_updates(),
+                capacity = max,
+                "update channel nearly full, consumer is falling behind"
+            );
+        } else if self.channel_congested && used < UPDATE_CHANNEL_WARN_RATIO / 2.0 {
+            self.channel_congested = false;
+            tracing::info!(pending = self.pending_updates(), "update channel drained");
         }
     }
 
-    /// Handles a transaction update, extracting undelegations.
-    fn handle_transaction_update(&self, txn: SubscribeUpdateTransaction) {
-        let Some(message) = txn
-            .transaction
-            .and_then(|t| t.transaction.zip(t.meta))
-            .and_then(|(t, m)| m.err.is_none().then_some(t.message))
-            .flatten()
-        else {
+    /// Removes a record from the subscription set, along with its per-record state.
+    fn unsubscribe_record(&mut self, record: &Pubkey) {
+        if self.subscriptions.remove(record) {
+            tracing::debug!(record = %PubkeyDisplay(record), "unsubscribed");
+            self.subscriptions_changed();
+            self.schedule_filter_update();
+        }
+        self.last_records.remove(record);
+        self.record_programs.remove(record);
+        self.states.remove(record);
+        self.last_activity.remove(record);
+        if let Some(seen) = &mut self.seen_accounts {
+            seen.remove(record);
+        }
+    }
+
+    /// Records activity of a subscribed record, postponing its expiry.
+    fn touch(&mut self, record: &Pubkey) {
+        let Some(ttl) = self.config.subscription_ttl else {
             return;
         };
+        if !self.subscriptions.contains(record) {
+            return;
+        }
 
-        let accounts = &message.account_keys;
+        let now = time::Instant::now();
+        self.last_activity.insert(*record, now);
+        self.expiry_deadline.get_or_insert(now + ttl);
+    }
 
-        let is_undelegate = |ix: &CompiledInstruction| {
-            let program_id = accounts.get(ix.program_id_index as usize)?;
-            (program_id == DELEGATION_PROGRAM_PUBKEY).then_some(())?;
+    /// Unsubscribes the records idle for longer than the subscription TTL.
+    ///
+    /// Runs on the syncer task like update handling, so an update either
+    /// refreshes the record before the sweep or finds it unsubscribed after.
+    fn expire_idle(&mut self) {
+        self.expiry_deadline = None;
+        let Some(ttl) = self.config.subscription_ttl else {
+            return;
+        };
 
-            let (discriminator, _) = ix.data.split_at_checked(DISCRIMINATOR_LEN)?;
-            (discriminator[0] == UNDELEGATE_DISCRIMINATOR).then_some(())?;
+        let now = time::Instant::now();
+        let expired: Vec<Pubkey> = self
+            .last_activity
+            .iter()
+            .filter(|(_, last)| now.duration_since(**last) >= ttl)
+            .map(|(record, _)| *record)
+            .collect();
+        for record in expired {
+            tracing::debug!(record = %PubkeyDisplay(&record), "subscription expired");
+            self.unsubscribe_record(&record);
+            self.emit(AccountUpdate::SubscriptionExpired { record });
+        }
 
-            ix.accounts
-                .get(DELEGATION_RECORD_ACCOUNT_INDEX)
-                .and_then(|&idx| accounts.get(idx as usize))
-        };
+        self.expiry_deadline = self.last_activity.values().min().map(|last| *last + ttl);
+    }
 
-        for record_bytes in message.instructions.iter().filter_map(is_undelegate) {
-            let Ok(record) = Pubkey::try_from(record_bytes.as_slice()) else {
-                continue;
-            };
+    /// Adds records to the subscription set and replies with the slot at the
+    /// given commitment, or buffers the request while warming up.
+    ///
+    /// A single filter update covers the whole batch. A batch that would grow
+    /// the set past [`DlpSyncConfig::max_subscriptions`] is rejected as a whole.
+    /// A batch is reported as delegated only if all of its records are.
+    fn subscribe_records(
+        &mut self,
+        records: Vec<Pubkey>,
+        commitment: Option<Commitment>,
+        slot_tx: SubscribeResponder,
+    ) {
+        if self.warmup_deadline.is_some() {
+            self.warmup_subscriptions
+                .push((records, commitment, slot_tx));
+            return;
+        }
 
-            let update = AccountUpdate::Undelegated {
-                record,
-                slot: txn.slot,
-            };
+        let limit = self.config.max_subscriptions;
+        let added = records
+            .iter()
+            .filter(|record| !self.subscriptions.contains(*record))
+            .collect::<HashSet<_>>()
+            .len();
+        if self.subscriptions.len() + added > limit {
+            tracing::warn!(
+                requested = added,
+                subscriptions = self.subscriptions.len(),
+                limit,
+                "subscription limit reached, rejecting subscribe"
+            );
+            self.metrics.inc_subscriptions_rejected();
+            let _ = slot_tx.send(Err(RequesterError::SubscriptionLimit { limit }));
+            return;
+        }
 
-            if let Err(error) = self.updates.try_send(update) {
-                tracing::error!(%error, "failed to send undelegation update");
+        let currently_delegated = records
+            .iter()
+            .all(|record| self.states.contains_key(record));
+        let before = self.subscriptions.len();
+        for record in records {
+            if self.subscriptions.insert(record) {
+                tracing::debug!(record = %PubkeyDisplay(&record), "subscribed");
             }
+            self.touch(&record);
         }
+        if self.subscriptions.len() != before {
+            self.subscriptions_changed();
+            self.schedule_filter_update();
+        }
+        let _ = slot_tx.send(Ok(SubscribeResult {
+            slot: self.slot_at(commitment),
+            currently_delegated,
+        }));
     }
 
-    /// Establishes a connection to the Laserstream and performs health check.
-    ///
-    /// Subscribes to:
-    /// - Account updates for delegation records (by owner and data size)
-    /// - Transaction updates that touch the delegation program
-    /// - Slot updates for tracking confirmed slots
-    async fn connect(config: LaserstreamConfig) -> Result<LaserStream, DlpSyncError> {
-        let mut accounts = HashMap::new();
-        let mut slots = HashMap::new();
-        let mut transactions = HashMap::new();
-
-        // Subscribe to delegation record accounts
-        let account_filter = SubscribeRequestFilterAccounts {
-            owner: vec![DELEGATION_PROGRAM.into()],
-            filters: vec![SubscribeRequestFilterAccountsFilter {
-                filter: Some(Filter::Datasize(DELEGATION_RECORD_SIZE)),
-            }],
-            ..Default::default()
-        };
-        accounts.insert("delegations".into(), account_filter);
+    /// Publishes the subscription count to the metrics and the syncer span.
+    fn subscriptions_changed(&self) {
+        let count = self.subscriptions.len();
+        self.metrics.set_active_subscriptions(count);
+        tracing::Span::current().record("subscriptions", count);
+    }
 
-        // Subscribe to undelegation transactions
-        let tx_filter = SubscribeRequestFilterTransactions {
-            account_include: vec![DELEGATION_PROGRAM.into()],
-            ..Default::default()
-        };
-        transactions.insert("undelegations".into(), tx_filter);
+    /// Ends the warm-up period, applying buffered subscriptions and marking the syncer ready.
+    fn finish_warmup(&mut self) {
+        self.warmup_deadline = None;
 
-        // Subscribe to all slot updates
-        slots.insert("slots".into(), Default::default());
+        for (records, commitment, slot_tx) in std::mem::take(&mut self.warmup_subscriptions) {
+            self.subscribe_records(records, commitment, slot_tx);
+        }
 
-        let request = SubscribeRequest {
-            accounts,
-            slots,
-            transactions,
-            ..Default::default()
-        };
+        self.connected.send_replace(true);
+    }
 
-        let (stream, handle) = client::subscribe(config, request);
-        let mut stream = Box::pin(stream);
+    /// Ends the bootstrap window, emitting the collected delegations followed
+    /// by [`AccountUpdate::SnapshotComplete`].
+    fn finish_bootstrap(&mut self) {
+        self.bootstrap_deadline = None;
 
-        // Send ping to establish connection
-        handle
-            .write(SubscribeRequest {
-                ping: Some(SubscribeRequestPing { id: 0 }),
-                ..Default::default()
-            })
-            .await
-            .map_err(DlpSyncError::LaserStream)?;
+        let snapshot = self.snapshot.take().unwrap_or_default();
+        let records = snapshot.len();
+        for update in snapshot.into_values() {
+            self.emit(update);
+        }
+        tracing::info!(records, "bootstrap snapshot complete");
+        self.emit(AccountUpdate::SnapshotComplete { records });
+    }
 
-        // Health check: wait for first update with timeout
-        time::timeout(Duration::from_secs(5), stream.next())
+    /// Handles an update from the Laserstream.
+    fn handle_update(&mut self, result: Result<SubscribeUpdate, LaserstreamError>) {
+        use UpdateOneof::*;
+
+        // The stream reconnects internally after yielding an error, so the
+        // next successful item marks the connection as re-established.
+        let connected = result.is_ok() && self.warmup_deadline.is_none();
+        let changed = self
+            .connected
+            .send_if_modified(|c| std::mem::replace(c, connected) != connected);
+
+        // The client replays the last written subscription on each of its
+        // reconnects, so tracked records survive the blip without a re-send.
+        if changed && connected {
+            tracing::info!(
+                records = self.subscriptions.len(),
+                "stream reconnected, tracked records resubscribed by the client"
+            );
+        }
+
+        self.last_item = time::Instant::now();
+
+        // Report transitions between a live stream and reconnect attempts
+        match &result {
+            Ok(_) if self.reconnect_attempt > 0 => {
+                self.reconnect_attempt = 0;
+                // The old stream recovered before its replacement was due
+                self.reconnect_deadline = None;
+                self.emit(AccountUpdate::ConnectionStatus(ConnStatus::Connected));
+            }
+            Err(_) => {
+                self.reconnect_attempt += 1;
+                self.metrics.inc_reconnects();
+                self.emit(AccountUpdate::ConnectionStatus(ConnStatus::Reconnecting {
+                    attempt: self.reconnect_attempt,
+                    delay: None,
+                }));
+            }
+            Ok(_) => {}
+        }
+
+        if result.is_ok() {
+            self.last_error = None;
+        }
+
+        let (filters, update) = match result {
+            Ok(u) => {
+                if self.config.raw_passthrough {
+                    self.emit(AccountUpdate::Raw(Box::new(u.clone())));
+                }
+                match u.update_oneof {
+                    Some(update) => (u.filters, update),
+                    None => return,
+                }
+            }
+            Err(error) => {
+                tracing::warn!(%error, "error during stream processing");
+                self.last_error = Some(error.to_string());
+                return;
+            }
+        };
+
+        match update {
+            Account(acc) => self.handle_account_update(acc),
+            Slot(slot) => self.handle_slot_update(slot),
+            Transaction(txn) => {
+                self.forward_raw_transaction(filters, &txn);
+                self.handle_transaction_update(txn);
+            }
+            _ => {}
+        }
+    }
+
+    /// Reports a forward jump of processed slots beyond the configured threshold.
+    ///
+    /// Only processed slots are compared with each other: other commitment
+    /// levels trail behind, and backward or repeated slots are not gaps.
+    fn check_slot_gap(&self, slot: Slot) {
+        let Some(threshold) = self.config.slot_gap_threshold else {
+            return;
+        };
+        let last = self.commitment_slots[Commitment::Processed as usize];
+        let gap = slot.saturating_sub(last);
+        if last > 0 && gap > threshold {
+            self.metrics.inc_slot_gaps();
+            tracing::warn!(
+                from = last,
+                to = slot,
+                gap,
+                "slot gap detected, stream data may have been dropped"
+            );
+        }
+    }
+
+    /// Handles a slot status update, releasing or discarding held undelegations.
+    fn handle_slot_update(&mut self, update: SubscribeUpdateSlot) {
+        // Lower commitment levels arrive late, so the latest slot only moves forward
+        self.slot = self.slot.max(update.slot);
+        self.metrics.set_current_slot(self.slot);
+        tracing::Span::current().record("slot", self.slot);
+        self.first_slot.get_or_insert(update.slot);
+
+        let status = SlotStatus::from(update.status());
+        if status == SlotStatus::Processed {
+            self.check_slot_gap(update.slot);
+        }
+        if let Some(commitment) = status.commitment() {
+            self.commitment_slots[commitment as usize] = update.slot;
+        }
+        if self.config.slot_status_updates {
+            self.emit(AccountUpdate::SlotStatus {
+                slot: update.slot,
+                status,
+            });
+        }
+        if self.config.slot_ordered {
+            self.release_ordered(update.slot, status);
+        }
+
+        let Some(required) = self.config.undelegation_commitment else {
+            return;
+        };
+
+        if status == SlotStatus::Dead {
+            if let Some(dropped) = self.pending_undelegations.remove(&update.slot) {
+                tracing::debug!(
+                    slot = update.slot,
+                    count = dropped.len(),
+                    "discarding undelegations from dead slot"
+                );
+            }
+            return;
+        }
+
+        let Some(commitment) = status.commitment() else {
+            return;
+        };
+
+        if commitment >= required {
+            self.committed_slots.insert(update.slot);
+            for undelegation in self
+                .pending_undelegations
+                .remove(&update.slot)
+                .unwrap_or_default()
+            {
+                self.emit(undelegation);
+            }
+        }
+
+        // Anything still held below a finalized slot was on an abandoned fork.
+        if commitment == Commitment::Finalized {
+            let retained = self.pending_undelegations.split_off(&update.slot);
+            let abandoned = std::mem::replace(&mut self.pending_undelegations, retained);
+            for (slot, dropped) in abandoned {
+                tracing::debug!(
+                    slot,
+                    count = dropped.len(),
+                    "discarding undelegations from abandoned slot"
+                );
+            }
+        }
+    }
+
+    /// Releases the updates held for slot ordering up to a newly confirmed slot.
+    fn release_ordered(&mut self, slot: Slot, status: SlotStatus) {
+        if status == SlotStatus::Dead {
+            if let Some(dropped) = self.reorder.remove(&slot) {
+                tracing::debug!(
+                    slot,
+                    count = dropped.len(),
+                    "discarding updates from dead slot"
+                );
+            }
+            return;
+        }
+        if !matches!(status, SlotStatus::Confirmed | SlotStatus::Finalized) {
+            return;
+        }
+
+        if slot <= self.ordered_through {
+            return;
+        }
+        self.ordered_through = slot;
+        let held = self.reorder.split_off(&(slot + 1));
+        for update in std::mem::replace(&mut self.reorder, held)
+            .into_values()
+            .flatten()
+        {
+            self.emit(update);
+        }
+    }
+
+    /// Handles an account (delegation record) update.
+    fn handle_account_update(&mut self, acc: SubscribeUpdateAccount) {
+        let Some(account) = acc.account else { return };
+        self.metrics.inc_accounts_received();
+
+        if acc.slot < self.min_slot {
+            return;
+        }
+
+        if account.pubkey.len() != PUBKEY_LEN {
+            return;
+        }
+
+        let Ok(record) = Pubkey::try_from(account.pubkey.as_slice()) else {
+            return;
+        };
+
+        if !self.is_tracked(&record) {
+            return;
+        }
+
+        let closed = account.lamports == 0;
+        let Some(program) = self.record_program(&record, &account.owner, closed) else {
+            return;
+        };
+        self.touch(&record);
+
+        // A closed record carries no data, so report it as undelegated rather
+        // than as an empty delegation
+        if closed {
+            self.emit_undelegation(
+                program,
+                record,
+                acc.slot,
+                UndelegationSource::AccountClose,
+                None,
+                true,
+            );
+            return;
+        }
+
+        if self.subscriptions.contains(&record) {
+            self.record_programs.insert(record, program);
+        }
+
+        // The size filter is applied server-side, but is not relied upon
+        if let Some(size) = self.config.record_size {
+            if account.data.len() as u64 != size {
+                tracing::warn!(
+                    record = %PubkeyDisplay(&record),
+                    expected = size,
+                    actual = account.data.len(),
+                    "dropping delegation record update with unexpected size"
+                );
+                return;
+            }
+        }
+
+        if let Some(seen) = &mut self.seen_accounts {
+            let mut hasher = DefaultHasher::new();
+            account.data.hash(&mut hasher);
+            let state = (acc.slot, hasher.finish());
+            if seen.get(&record) == Some(&state) {
+                tracing::trace!(
+                    record = %PubkeyDisplay(&record),
+                    slot = acc.slot,
+                    "skipping repeated account update"
+                );
+                return;
+            }
+            seen.insert(record, state);
+        }
+
+        // Only subscribed records are kept, so firehose mode does not grow the map
+        if self.subscriptions.contains(&record)
+            && self
+                .states
+                .get(&record)
+                .is_none_or(|(_, slot)| *slot <= acc.slot)
+        {
+            self.states.insert(record, (account.data.clone(), acc.slot));
+        }
+
+        if self.config.delta_updates {
+            let previous = self.last_records.insert(record, account.data.clone());
+            // The snapshot keeps full records only, so no deltas while bootstrapping
+            if let Some(changed_fields) = previous
+                .filter(|_| self.snapshot.is_none())
+                .and_then(|prev| diff_fields(&prev, &account.data))
+            {
+                self.emit(AccountUpdate::DelegatedDelta {
+                    program,
+                    record,
+                    changed_fields,
+                    slot: acc.slot,
+                });
+                return;
+            }
+        }
+
+        if self.config.parse_records {
+            match DelegationRecord::try_from_bytes(&account.data) {
+                Ok(parsed) => {
+                    self.emit(AccountUpdate::DelegatedParsed {
+                        program,
+                        record,
+                        parsed,
+                        slot: acc.slot,
+                    });
+                    return;
+                }
+                Err(error) => tracing::warn!(
+                    record = %PubkeyDisplay(&record),
+                    %error,
+                    "failed to parse delegation record, emitting raw data"
+                ),
+            }
+        }
+
+        // Record accounts are owned by their program, which stands in for a malformed owner
+        let owner = Pubkey::try_from(account.owner.as_slice()).unwrap_or(program);
+        self.emit(AccountUpdate::Delegated {
+            program,
+            record,
+            data: account.data,
+            slot: acc.slot,
+            write_version: account.write_version,
+            owner,
+        });
+    }
+
+    /// Forwards a transaction matching any of the extra filters as-is.
+    fn forward_raw_transaction(&mut self, filters: Vec<String>, txn: &SubscribeUpdateTransaction) {
+        let extra = &self.config.extra_transaction_filters;
+        let filters: Vec<String> = filters
+            .into_iter()
+            .filter(|label| label != UNDELEGATIONS_FILTER && extra.contains_key(label))
+            .collect();
+
+        if filters.is_empty() || txn.slot < self.min_slot {
+            return;
+        }
+
+        self.emit(AccountUpdate::RawTransaction {
+            filters,
+            transaction: Box::new(txn.clone()),
+        });
+    }
+
+    /// Handles a transaction update, extracting undelegations.
+    fn handle_transaction_update(&mut self, txn: SubscribeUpdateTransaction) {
+        if txn.slot < self.min_slot {
+            return;
+        }
+
+        let Some(info) = txn.transaction else { return };
+        // Votes never touch the delegation program, whatever the filter lets through
+        if info.is_vote {
+            return;
+        }
+        let signature = info.signature;
+
+        let Some((transaction, meta)) = info.transaction.zip(info.meta) else {
+            return;
+        };
+        let succeeded = meta.err.is_none();
+        let Some(message) = transaction
+            .message
+            .filter(|_| succeeded || self.config.include_failed_undelegations)
+        else {
+            return;
+        };
+
+        // Versioned transactions index accounts loaded from address lookup
+        // tables after the static keys, writable ones first
+        let accounts: Vec<&[u8]> = message
+            .account_keys
+            .iter()
+            .chain(&meta.loaded_writable_addresses)
+            .chain(&meta.loaded_readonly_addresses)
+            .map(Vec::as_slice)
+            .collect();
+        let discriminators = &self.config.undelegate_discriminators;
+        let record_index = self.config.undelegate_record_index;
+        let programs = &self.config.delegation_programs;
+
+        let program_at = |program_index: u32| {
+            let program_id = accounts.get(program_index as usize)?;
+            programs.iter().find(|p| p[..] == **program_id).copied()
+        };
+        let account_at = |ix_accounts: &[u8], index: usize| {
+            let key = ix_accounts
+                .get(index)
+                .and_then(|&idx| accounts.get(idx as usize))?;
+            Pubkey::try_from(*key).ok()
+        };
+
+        // (program id index, account indices, data) of a top-level or inner instruction
+        let is_undelegate = |&(program_index, ix_accounts, data): &(u32, &[u8], &[u8])| {
+            let program = program_at(program_index)?;
+
+            discriminators
+                .iter()
+                .any(|d| !d.is_empty() && data.starts_with(d))
+                .then_some(())?;
+
+            Some((program, account_at(ix_accounts, record_index)?))
+        };
+        let is_delegate = |&(program_index, ix_accounts, data): &(u32, &[u8], &[u8])| {
+            let program = program_at(program_index)?;
+            (DlpInstruction::from_data(data)? == DlpInstruction::Delegate).then_some(())?;
+
+            Some((
+                program,
+                account_at(ix_accounts, DELEGATE_RECORD_ACCOUNT_INDEX)?,
+                account_at(ix_accounts, DELEGATE_ACCOUNT_INDEX)?,
+                account_at(ix_accounts, DELEGATE_OWNER_PROGRAM_INDEX)?,
+            ))
+        };
+
+        // Undelegations invoked via CPI only show up in the inner instructions
+        let top_level = message
+            .instructions
+            .iter()
+            .map(|ix| (ix.program_id_index, &ix.accounts[..], &ix.data[..]));
+        let inner = meta
+            .inner_instructions
+            .iter()
+            .flat_map(|inner| &inner.instructions)
+            .map(|ix| (ix.program_id_index, &ix.accounts[..], &ix.data[..]));
+
+        let instructions: Vec<(u32, &[u8], &[u8])> = top_level.chain(inner).collect();
+        let records: Vec<(Pubkey, Pubkey)> =
+            instructions.iter().filter_map(is_undelegate).collect();
+        // Failed delegations leave nothing behind, so they are never reported
+        let delegations: Vec<(Pubkey, Pubkey, Pubkey, Pubkey)> = if succeeded {
+            instructions.iter().filter_map(is_delegate).collect()
+        } else {
+            Vec::new()
+        };
+
+        if records.is_empty() && delegations.is_empty() {
+            return;
+        }
+
+        let Ok(signature) = Signature::try_from(signature.as_slice()) else {
+            tracing::warn!(
+                slot = txn.slot,
+                len = signature.len(),
+                "skipping delegation program transaction with malformed signature"
+            );
+            return;
+        };
+
+        if let Some(seen) = &mut self.seen_signatures {
+            if !seen.insert(signature) {
+                tracing::debug!(
+                    slot = txn.slot,
+                    signature = %bs58::encode(signature).into_string(),
+                    "skipping replayed delegation program transaction"
+                );
+                return;
+            }
+        }
+
+        for (program, record, account, owner) in delegations {
+            if self.is_tracked(&record) {
+                self.emit(AccountUpdate::DelegatedByTransaction {
+                    program,
+                    record,
+                    account,
+                    owner,
+                    slot: txn.slot,
+                    signature,
+                });
+            }
+        }
+
+        let mut batch = Vec::new();
+        for (program, record) in records {
+            if !self.is_tracked(&record) {
+                continue;
+            }
+            if self.config.batch_undelegations {
+                if self.note_undelegation(
+                    &program,
+                    &record,
+                    txn.slot,
+                    UndelegationSource::Transaction,
+                    succeeded,
+                ) {
+                    batch.push(record);
+                }
+            } else {
+                self.emit_undelegation(
+                    program,
+                    record,
+                    txn.slot,
+                    UndelegationSource::Transaction,
+                    Some(signature),
+                    succeeded,
+                );
+            }
+            // Only after emitting, so the undelegation itself is not lost
+            if succeeded && self.config.auto_unsubscribe_on_undelegate {
+                self.unsubscribe_record(&record);
+            }
+        }
+
+        if !batch.is_empty() {
+            self.emit_committed(
+                txn.slot,
+                AccountUpdate::UndelegatedBatch {
+                    records: batch,
+                    slot: txn.slot,
+                    signature,
+                    succeeded,
+                },
+            );
+        }
+    }
+
+    /// Emits an undelegation, unless the same record was already reported
+    /// undelegated in that slot (by the same source, in dual-source mode).
+    fn emit_undelegation(
+        &mut self,
+        program: Pubkey,
+        record: Pubkey,
+        slot: Slot,
+        source: UndelegationSource,
+        signature: Option<Signature>,
+        succeeded: bool,
+    ) {
+        if !self.note_undelegation(&program, &record, slot, source, succeeded) {
+            return;
+        }
+
+        let undelegation = AccountUpdate::Undelegated {
+            program,
+            record,
+            slot,
+            source,
+            signature,
+            succeeded,
+        };
+        self.emit_committed(slot, undelegation);
+    }
+
+    /// Records a detected undelegation, returning whether it is new and
+    /// should be reported.
+    fn note_undelegation(
+        &mut self,
+        program: &Pubkey,
+        record: &Pubkey,
+        slot: Slot,
+        source: UndelegationSource,
+        succeeded: bool,
+    ) -> bool {
+        // Unless reporting each source, the first detection in a slot wins
+        let dedup_source = self.config.dual_source_undelegations.then_some(source);
+        if !self
+            .undelegations
+            .insert((*record, slot, dedup_source, succeeded))
+        {
+            return false;
+        }
+        self.metrics.inc_undelegations_detected();
+        self.touch(record);
+        tracing::debug!(
+            record = %PubkeyDisplay(record),
+            program = %PubkeyDisplay(program),
+            slot,
+            ?source,
+            succeeded,
+            "undelegation detected"
+        );
+        // A failed attempt leaves the record delegated
+        if succeeded {
+            self.last_records.remove(record);
+            self.states.remove(record);
+        }
+        true
+    }
+
+    /// Emits an undelegation update, or holds it until its slot reaches the
+    /// configured undelegation commitment.
+    fn emit_committed(&mut self, slot: Slot, undelegation: AccountUpdate) {
+        if self
+            .config
+            .undelegation_commitment
+            .is_some_and(|c| c > Commitment::Processed)
+            && !self.committed_slots.contains(&slot)
+        {
+            self.pending_undelegations
+                .entry(slot)
+                .or_default()
+                .push(undelegation);
+            return;
+        }
+
+        self.emit(undelegation);
+    }
+
+    /// Whether updates for the given record should be forwarded to the consumer.
+    fn is_tracked(&self, record: &Pubkey) -> bool {
+        self.config.firehose || self.subscriptions.contains(record)
+    }
+
+    /// Logs an update in place of emitting it, delegation events at info level.
+    fn log_dry_run(update: &AccountUpdate) {
+        match update {
+            AccountUpdate::Delegated {
+                program,
+                record,
+                slot,
+                ..
+            }
+            | AccountUpdate::DelegatedParsed {
+                program,
+                record,
+                slot,
+                ..
+            }
+            | AccountUpdate::DelegatedDelta {
+                program,
+                record,
+                slot,
+                ..
+            }
+            | AccountUpdate::DelegatedByTransaction {
+                program,
+                record,
+                slot,
+                ..
+            } => tracing::info!(
+                record = %PubkeyDisplay(record),
+                program = %PubkeyDisplay(program),
+                slot,
+                "dry run: delegation detected"
+            ),
+            AccountUpdate::Undelegated {
+                program,
+                record,
+                slot,
+                source,
+                succeeded,
+                ..
+            } => tracing::info!(
+                record = %PubkeyDisplay(record),
+                program = %PubkeyDisplay(program),
+                slot,
+                ?source,
+                succeeded,
+                "dry run: undelegation detected"
+            ),
+            AccountUpdate::UndelegatedBatch {
+                records,
+                slot,
+                succeeded,
+                ..
+            } => {
+                for record in records {
+                    tracing::info!(
+                        record = %PubkeyDisplay(record),
+                        slot,
+                        succeeded,
+                        "dry run: undelegation detected"
+                    );
+                }
+            }
+            update => tracing::debug!(?update, "dry run: update"),
+        }
+    }
+
+    /// Sends an update to the consumer, applying the backpressure policy if the channel is full.
+    fn emit(&mut self, update: AccountUpdate) {
+        if self.config.slot_ordered {
+            if let Some(slot) = update.slot().filter(|slot| *slot > self.ordered_through) {
+                self.reorder.entry(slot).or_default().push(update);
+                return;
+            }
+        }
+
+        self.check_congestion();
+
+        // While bootstrapping, only the latest delegation of each record is kept
+        if let Some(snapshot) = &mut self.snapshot {
+            match &update {
+                AccountUpdate::Delegated { record, .. }
+                | AccountUpdate::DelegatedParsed { record, .. } => {
+                    snapshot.insert(*record, update);
+                    return;
+                }
+                AccountUpdate::Undelegated {
+                    record,
+                    succeeded: true,
+                    ..
+                } => {
+                    snapshot.remove(record);
+                }
+                AccountUpdate::UndelegatedBatch {
+                    records,
+                    succeeded: true,
+                    ..
+                } => {
+                    for record in records {
+                        snapshot.remove(record);
+                    }
+                }
+                AccountUpdate::SubscriptionExpired { record } => {
+                    snapshot.remove(record);
+                }
+                _ => {}
+            }
+        }
+
+        if self.config.dry_run {
+            Self::log_dry_run(&update);
+            return;
+        }
+
+        if self.config.backpressure == Backpressure::DropNewest {
+            if let Err(error) = self.updates.try_send(update) {
+                self.metrics.inc_updates_dropped();
+                tracing::error!(%error, "failed to send update");
+            }
+            return;
+        }
+
+        // Preserve ordering: once anything is queued, everything queues.
+        let update = if self.outbox.is_empty() {
+            match self.updates.try_send(update) {
+                Ok(()) => return,
+                Err(TrySendError::Full(update)) => update,
+                Err(error @ TrySendError::Closed(_)) => {
+                    self.metrics.inc_updates_dropped();
+                    tracing::error!(%error, "failed to send update");
+                    return;
+                }
+            }
+        } else {
+            update
+        };
+
+        if self.config.backpressure == Backpressure::DropOldest
+            && self.outbox.len() >= self.config.update_capacity.max(1)
+        {
+            let stale = update
+                .record()
+                .and_then(|record| self.outbox.iter().position(|u| u.record() == Some(record)))
+                .unwrap_or(0);
+            self.outbox.remove(stale);
+            self.metrics.inc_updates_dropped();
+            tracing::warn!("update channel full, dropped oldest queued update");
+        }
+        self.outbox.push_back(update);
+    }
+
+    /// Builds the subscription request for the given configuration and tracked records.
+    ///
+    /// Subscribes to:
+    /// - Account updates for tracked delegation records (by owner, data size and pubkey)
+    /// - Transaction updates that touch the delegation program
+    /// - Slot updates for tracking confirmed slots
+    /// - Any extra transaction filters from the configuration
+    ///
+    /// In firehose mode the account filter is not narrowed to the tracked records.
+    fn subscribe_request(
+        config: &DlpSyncConfig,
+        subscriptions: &HashSet<Pubkey>,
+    ) -> SubscribeRequest {
+        let mut accounts = HashMap::new();
+        let mut slots = HashMap::new();
+        let mut transactions = HashMap::new();
+        let programs: Vec<String> = config
+            .delegation_programs
+            .iter()
+            .map(|program| PubkeyDisplay(program).to_string())
+            .collect();
+
+        // Subscribe to delegation record accounts, one filter per program,
+        // narrowed server-side to the tracked records unless in firehose mode
+        if config.firehose || !subscriptions.is_empty() {
+            let account: Vec<String> = if config.firehose {
+                Vec::new()
+            } else {
+                subscriptions
+                    .iter()
+                    .map(|record| PubkeyDisplay(record).to_string())
+                    .collect()
+            };
+            for program in &programs {
+                let account_filter = SubscribeRequestFilterAccounts {
+                    account: account.clone(),
+                    owner: vec![program.clone()],
+                    filters: config
+                        .record_size
+                        .map(|size| SubscribeRequestFilterAccountsFilter {
+                            filter: Some(Filter::Datasize(size)),
+                        })
+                        .into_iter()
+                        .collect(),
+                    ..Default::default()
+                };
+                accounts.insert(format!("{DELEGATIONS_FILTER}:{program}"), account_filter);
+            }
+        }
+
+        // Subscribe to the tracked record addresses regardless of owner and
+        // size, so their closing is delivered too. Firehose mode has no
+        // address list and relies on undelegation transactions instead.
+        if !config.firehose && !subscriptions.is_empty() {
+            let close_filter = SubscribeRequestFilterAccounts {
+                account: subscriptions
+                    .iter()
+                    .map(|record| PubkeyDisplay(record).to_string())
+                    .collect(),
+                ..Default::default()
+            };
+            accounts.insert(CLOSES_FILTER.into(), close_filter);
+        }
+
+        // Subscribe to undelegation transactions of any of the programs
+        let tx_filter = SubscribeRequestFilterTransactions {
+            account_include: programs,
+            ..Default::default()
+        };
+        transactions.insert(UNDELEGATIONS_FILTER.into(), tx_filter);
+
+        // Subscribe to extra passthrough transactions
+        for (label, filter) in &config.extra_transaction_filters {
+            if label != UNDELEGATIONS_FILTER {
+                transactions.insert(label.clone(), filter.clone());
+            }
+        }
+
+        // Subscribe to all slot updates
+        slots.insert("slots".into(), Default::default());
+
+        SubscribeRequest {
+            accounts,
+            slots,
+            transactions,
+            commitment: config.commitment.map(|c| CommitmentLevel::from(c) as i32),
+            ..Default::default()
+        }
+    }
+
+    /// Connects to the first endpoint that passes the health check, returning
+    /// its index along with the connection.
+    async fn connect_first(
+        config: &LaserstreamConfig,
+        endpoints: &[String],
+        request: SubscribeRequest,
+        health_check_timeout: Option<Duration>,
+    ) -> Result<(usize, LaserStream, StreamHandle), DlpSyncError> {
+        let mut last_error = DlpSyncError::Connection("no endpoint configured");
+        for (index, endpoint) in endpoints.iter().enumerate() {
+            let config = LaserstreamConfig {
+                endpoint: endpoint.clone(),
+                ..config.clone()
+            };
+            match Self::connect(config, request.clone(), health_check_timeout).await {
+                Ok((stream, handle)) => return Ok((index, stream, handle)),
+                Err(error) => {
+                    tracing::warn!(
+                        endpoint = %redact_endpoint(endpoint),
+                        ?error,
+                        "failed to connect to endpoint"
+                    );
+                    last_error = error;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Builds the Laserstream client configuration, without an endpoint.
+    fn laserstream_config(key: String, config: &DlpSyncConfig) -> LaserstreamConfig {
+        LaserstreamConfig {
+            api_key: key,
+            endpoint: String::new(),
+            channel_options: ChannelOptions {
+                accept_compression: (!config.accept_compression.is_empty())
+                    .then(|| config.accept_compression.clone())
+                    .or_else(|| config.channel_options.accept_compression.clone()),
+                send_compression: config
+                    .send_compression
+                    .or(config.channel_options.send_compression),
+                ..config.channel_options.clone()
+            },
+            max_reconnect_attempts: Some(config.max_reconnect_attempts),
+            replay: config.replay,
+        }
+    }
+
+    /// Establishes a connection to the Laserstream and performs health check.
+    async fn connect(
+        config: LaserstreamConfig,
+        request: SubscribeRequest,
+        health_check_timeout: Option<Duration>,
+    ) -> Result<(LaserStream, StreamHandle), DlpSyncError> {
+        let (stream, handle) = client::subscribe(config, request);
+        let mut stream = Box::pin(stream);
+
+        // The client sends the subscription itself and keeps the connection
+        // alive with its own pings, so nothing needs to be written here.
+
+        let Some(health_check_timeout) = health_check_timeout else {
+            return Ok((stream, handle));
+        };
+
+        // Health check: wait for first update with timeout
+        time::timeout(health_check_timeout, stream.next())
             .await
             .map_err(|_| DlpSyncError::Connection("health check timed out"))?
             .ok_or_else(|| DlpSyncError::Connection("stream closed before first update"))?
             .map_err(DlpSyncError::LaserStream)?;
 
-        Ok(stream)
+        Ok((stream, handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use helius_laserstream::{
+        grpc::SubscribeUpdateTransactionInfo,
+        solana::storage::confirmed_block::{
+            CompiledInstruction, InnerInstruction, InnerInstructions, Message, Transaction,
+            TransactionStatusMeta,
+        },
+        CompressionEncoding,
+    };
+
+    use super::*;
+    use crate::record::{RecordField, DELEGATION_RECORD_SIZE};
+
+    /// Program the test records belong to.
+    const PROGRAM: Pubkey = DELEGATION_PROGRAM_PUBKEY;
+
+    /// A tracked delegation record.
+    const RECORD: Pubkey = [7; 32];
+
+    /// A record nobody subscribed to.
+    const OTHER_RECORD: Pubkey = [8; 32];
+
+    /// Starts a syncer driven by a local stream.
+    fn start(
+        config: DlpSyncConfig,
+    ) -> (
+        DlpSyncChannelsRequester,
+        Receiver<AccountUpdate>,
+        LocalStream,
+    ) {
+        let (channels, local) = DlpSyncer::start_local(config);
+        let (requester, updates) = channels.split();
+        (requester, updates, local)
+    }
+
+    /// Receives the next update, failing if none arrives in time.
+    async fn next(updates: &mut Receiver<AccountUpdate>) -> AccountUpdate {
+        time::timeout(Duration::from_secs(1), updates.recv())
+            .await
+            .expect("no update in time")
+            .expect("update channel closed")
+    }
+
+    /// Data of a delegation record of the expected size.
+    fn record_data() -> Vec<u8> {
+        vec![1; DELEGATION_RECORD_SIZE as usize]
+    }
+
+    /// Builds a successful transaction invoking the delegation program with
+    /// `data`, passing `accounts` as the instruction accounts in order.
+    fn transaction(
+        slot: Slot,
+        signature: u8,
+        data: &[u8],
+        accounts: &[Pubkey],
+    ) -> SubscribeUpdateTransaction {
+        let account_keys = std::iter::once(&PROGRAM)
+            .chain(accounts)
+            .map(|key| key.to_vec())
+            .collect();
+        let instruction = CompiledInstruction {
+            program_id_index: 0,
+            accounts: (1..=accounts.len() as u8).collect(),
+            data: data.to_vec(),
+        };
+        SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: vec![signature; 64],
+                transaction: Some(Transaction {
+                    message: Some(Message {
+                        account_keys,
+                        instructions: vec![instruction],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                meta: Some(TransactionStatusMeta::default()),
+                ..Default::default()
+            }),
+            slot,
+        }
+    }
+
+    /// Builds a transaction undelegating `record`.
+    fn undelegation(slot: Slot, signature: u8, record: Pubkey) -> SubscribeUpdateTransaction {
+        let mut accounts = [[0; 32]; DELEGATION_RECORD_ACCOUNT_INDEX + 1];
+        accounts[DELEGATION_RECORD_ACCOUNT_INDEX] = record;
+        let data = [DlpInstruction::Undelegate.discriminator()];
+        transaction(slot, signature, &data, &accounts)
+    }
+
+    /// Pushes a transaction matching the undelegation filter.
+    fn push_transaction(local: &LocalStream, txn: SubscribeUpdateTransaction) {
+        local.push(SubscribeUpdate {
+            filters: vec![UNDELEGATIONS_FILTER.into()],
+            update_oneof: Some(UpdateOneof::Transaction(txn)),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn close_filter_tracks_record_addresses_only() {
+        let config = DlpSyncConfig::default();
+        let request = DlpSyncer::subscribe_request(&config, &HashSet::from([RECORD]));
+
+        let filter = &request.accounts[CLOSES_FILTER];
+        assert_eq!(filter.account, vec![PubkeyDisplay(&RECORD).to_string()]);
+        assert!(filter.owner.is_empty());
+        assert!(filter.filters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_closed_records_as_undelegated() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        local.push_record(PROGRAM, RECORD, record_data(), 10);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated {
+                record: RECORD,
+                slot: 10,
+                ..
+            }
+        ));
+
+        local.push_close(RECORD, 11);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                program: PROGRAM,
+                record: RECORD,
+                slot: 11,
+                source: UndelegationSource::AccountClose,
+                succeeded: true,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn firehose_forwards_unsubscribed_records() {
+        let config = DlpSyncConfig {
+            firehose: true,
+            ..Default::default()
+        };
+        let (_requester, mut updates, local) = start(config);
+
+        local.push_record(PROGRAM, OTHER_RECORD, record_data(), 10);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated {
+                record: OTHER_RECORD,
+                slot: 10,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn unsubscribed_records_are_skipped_without_firehose() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        local.push_record(PROGRAM, OTHER_RECORD, record_data(), 10);
+        local.push_record(PROGRAM, RECORD, record_data(), 11);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated {
+                record: RECORD,
+                slot: 11,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn start_times_out_on_a_silent_endpoint() {
+        // Accepts connections, but never answers them
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let config = DlpSyncConfig {
+            start_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+
+        let result = DlpSyncer::start_with_endpoints(vec![endpoint], "key".into(), config).await;
+        assert!(matches!(
+            result,
+            Err(DlpSyncError::Connection("startup timed out"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reports_transaction_undelegations() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        push_transaction(&local, undelegation(20, 1, RECORD));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                program: PROGRAM,
+                record: RECORD,
+                slot: 20,
+                source: UndelegationSource::Transaction,
+                signature: Some(_),
+                succeeded: true,
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn first_source_wins_by_default() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        push_transaction(&local, undelegation(20, 1, RECORD));
+        local.push_close(RECORD, 20);
+        local.push_record(PROGRAM, RECORD, record_data(), 21);
+
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                source: UndelegationSource::Transaction,
+                ..
+            }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 21, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn dual_source_reports_each_source_once() {
+        let config = DlpSyncConfig {
+            dual_source_undelegations: true,
+            ..Default::default()
+        };
+        let (requester, mut updates, local) = start(config);
+        requester.subscribe(RECORD).await.unwrap();
+
+        push_transaction(&local, undelegation(20, 1, RECORD));
+        push_transaction(&local, undelegation(20, 2, RECORD));
+        local.push_close(RECORD, 20);
+        local.push_close(RECORD, 20);
+        local.push_record(PROGRAM, RECORD, record_data(), 21);
+
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                slot: 20,
+                source: UndelegationSource::Transaction,
+                ..
+            }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                slot: 20,
+                source: UndelegationSource::AccountClose,
+                ..
+            }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 21, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn drops_updates_below_the_slot_floor() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.set_min_slot(15).await.unwrap();
+        // Requests are handled in order, so the floor is in place once this returns
+        requester.subscribe(RECORD).await.unwrap();
+
+        local.push_record(PROGRAM, RECORD, record_data(), 10);
+        push_transaction(&local, undelegation(12, 1, RECORD));
+        local.push_record(PROGRAM, RECORD, record_data(), 15);
+
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 15, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn holds_undelegations_until_their_slot_is_committed() {
+        let config = DlpSyncConfig {
+            undelegation_commitment: Some(Commitment::Finalized),
+            ..Default::default()
+        };
+        let (requester, mut updates, local) = start(config);
+        requester.subscribe(RECORD).await.unwrap();
+
+        push_transaction(&local, undelegation(20, 1, RECORD));
+        push_transaction(&local, undelegation(21, 2, RECORD));
+        local.push_record(PROGRAM, RECORD, record_data(), 22);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 22, .. }
+        ));
+
+        local.push_slot(20, Commitment::Finalized);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated { slot: 20, .. }
+        ));
+
+        // The fork of slot 21 is abandoned, so its undelegation never happened
+        local.push(SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot: 21,
+                status: helius_laserstream::grpc::SlotStatus::SlotDead as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        });
+        // Nor does a commitment arriving for it later revive it
+        local.push_slot(21, Commitment::Finalized);
+        local.push_record(PROGRAM, RECORD, record_data(), 23);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 23, .. }
+        ));
+    }
+
+    #[test]
+    fn merges_extra_transaction_filters() {
+        let swaps = SubscribeRequestFilterTransactions {
+            account_include: vec!["swaps".into()],
+            ..Default::default()
+        };
+        let mut config = DlpSyncConfig::default();
+        config
+            .extra_transaction_filters
+            .insert("swaps".into(), swaps.clone());
+        config
+            .extra_transaction_filters
+            .insert(UNDELEGATIONS_FILTER.into(), swaps.clone());
+
+        let request = DlpSyncer::subscribe_request(&config, &HashSet::new());
+        assert_eq!(request.transactions["swaps"], swaps);
+        // The reserved label keeps the built-in filter
+        assert_eq!(
+            request.transactions[UNDELEGATIONS_FILTER].account_include,
+            vec![PubkeyDisplay(&PROGRAM).to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn forwards_transactions_matching_extra_filters() {
+        let mut config = DlpSyncConfig::default();
+        config
+            .extra_transaction_filters
+            .insert("swaps".into(), Default::default());
+        let (_requester, mut updates, local) = start(config);
+
+        local.push(SubscribeUpdate {
+            filters: vec!["swaps".into(), "unknown".into()],
+            update_oneof: Some(UpdateOneof::Transaction(transaction(30, 1, &[], &[]))),
+            ..Default::default()
+        });
+        match next(&mut updates).await {
+            AccountUpdate::RawTransaction {
+                filters,
+                transaction,
+            } => {
+                assert_eq!(filters, vec!["swaps".to_string()]);
+                assert_eq!(transaction.slot, 30);
+            }
+            update => panic!("unexpected update: {update:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn await_connected_resolves_once_reconnected() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        let timeout = Duration::from_millis(50);
+        assert_eq!(requester.await_connected(timeout).await, Some(()));
+
+        local.push_error(LaserstreamError::ConnectionError("reset".into()));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::ConnectionStatus(ConnStatus::Reconnecting { attempt: 1, .. })
+        ));
+        assert_eq!(requester.await_connected(timeout).await, None);
+
+        local.push_slot(1, Commitment::Processed);
+        assert_eq!(requester.await_connected(timeout).await, Some(()));
+
+        // A terminated syncer never connects again
+        drop(local);
+        while !matches!(next(&mut updates).await, AccountUpdate::SyncTerminated(_)) {}
+        assert_eq!(requester.await_connected(timeout).await, None);
+    }
+
+    #[tokio::test]
+    async fn matches_each_undelegate_encoding() {
+        let anchor = vec![0xa5, 1, 2, 3, 4, 5, 6, 7];
+        let config = DlpSyncConfig {
+            undelegate_discriminators: vec![vec![3], anchor.clone()],
+            ..Default::default()
+        };
+        let (requester, mut updates, local) = start(config);
+        requester.subscribe(RECORD).await.unwrap();
+
+        let mut accounts = [[0; 32]; DELEGATION_RECORD_ACCOUNT_INDEX + 1];
+        accounts[DELEGATION_RECORD_ACCOUNT_INDEX] = RECORD;
+        for (slot, data) in [
+            (20, vec![3]),
+            (21, 3u64.to_le_bytes().to_vec()),
+            (22, [anchor, vec![9]].concat()),
+            // Commits and finalizations leave the record delegated
+            (23, vec![1]),
+            (24, vec![2]),
+        ] {
+            push_transaction(&local, transaction(slot, slot as u8, &data, &accounts));
+        }
+        local.push_record(PROGRAM, RECORD, record_data(), 25);
+
+        for expected in 20..=22 {
+            assert!(matches!(
+                next(&mut updates).await,
+                AccountUpdate::Undelegated { slot, .. } if slot == expected
+            ));
+        }
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 25, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn forwards_slot_status_updates_when_enabled() {
+        let config = DlpSyncConfig {
+            slot_status_updates: true,
+            ..Default::default()
+        };
+        let (_requester, mut updates, local) = start(config);
+
+        local.push_slot(5, Commitment::Processed);
+        local.push_slot(5, Commitment::Confirmed);
+        for expected in [SlotStatus::Processed, SlotStatus::Confirmed] {
+            assert!(matches!(
+                next(&mut updates).await,
+                AccountUpdate::SlotStatus { slot: 5, status } if status == expected
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn slot_status_updates_are_off_by_default() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        local.push_slot(5, Commitment::Processed);
+        local.push_record(PROGRAM, RECORD, record_data(), 5);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 5, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn buffers_subscriptions_during_warmup() {
+        let warmup = Duration::from_millis(100);
+        let config = DlpSyncConfig {
+            warmup,
+            ..Default::default()
+        };
+        let started = time::Instant::now();
+        let (requester, mut updates, local) = start(config);
+        assert_eq!(requester.await_connected(Duration::ZERO).await, None);
+
+        requester.subscribe(RECORD).await.unwrap();
+        assert!(started.elapsed() >= warmup);
+        assert_eq!(requester.await_connected(Duration::ZERO).await, Some(()));
+
+        local.push_record(PROGRAM, RECORD, record_data(), 10);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 10, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn tracks_the_first_and_latest_slot() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+        let health = requester.health().await.unwrap();
+        assert_eq!((health.first_slot, health.current_slot), (None, 0));
+
+        local.push_slot(100, Commitment::Processed);
+        local.push_slot(101, Commitment::Processed);
+        local.push_slot(100, Commitment::Confirmed);
+        // Once the record update arrives, the slots before it were handled
+        local.push_record(PROGRAM, RECORD, record_data(), 101);
+        next(&mut updates).await;
+
+        let health = requester.health().await.unwrap();
+        assert_eq!((health.first_slot, health.current_slot), (Some(100), 101));
+    }
+
+    #[tokio::test]
+    async fn skips_replayed_transactions() {
+        let config = DlpSyncConfig {
+            signature_dedup_capacity: Some(16),
+            ..Default::default()
+        };
+        let (requester, mut updates, local) = start(config);
+        requester.subscribe(RECORD).await.unwrap();
+
+        // Only the signature tells the replay apart from a new undelegation
+        push_transaction(&local, undelegation(20, 1, RECORD));
+        push_transaction(&local, undelegation(21, 1, RECORD));
+        local.push_record(PROGRAM, RECORD, record_data(), 22);
+
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated { slot: 20, .. }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 22, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn reconnect_updates_swaps_the_consumer() {
+        let (requester, mut old, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        local.push_record(PROGRAM, RECORD, record_data(), 10);
+        assert!(matches!(
+            next(&mut old).await,
+            AccountUpdate::Delegated { slot: 10, .. }
+        ));
+
+        let mut new = requester.reconnect_updates().await.unwrap();
+        local.push_record(PROGRAM, RECORD, record_data(), 11);
+        assert!(matches!(
+            next(&mut new).await,
+            AccountUpdate::Delegated { slot: 11, .. }
+        ));
+        assert!(old.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_newer_slots_under_pressure() {
+        let config = DlpSyncConfig {
+            update_capacity: 2,
+            backpressure: Backpressure::DropOldest,
+            ..Default::default()
+        };
+        let (requester, mut updates, local) = start(config);
+        requester.subscribe(RECORD).await.unwrap();
+        requester.subscribe(OTHER_RECORD).await.unwrap();
+
+        for slot in 10..=15 {
+            local.push_record(PROGRAM, RECORD, record_data(), slot);
+        }
+        local.push_record(PROGRAM, OTHER_RECORD, record_data(), 16);
+
+        // The channel holds the first two, the queue keeps the newest ones
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            match next(&mut updates).await {
+                AccountUpdate::Delegated { record, slot, .. } => received.push((record, slot)),
+                update => panic!("unexpected update {update:?}"),
+            }
+        }
+        assert_eq!(
+            received,
+            vec![(RECORD, 10), (RECORD, 11), (RECORD, 15), (OTHER_RECORD, 16)]
+        );
+    }
+
+    #[tokio::test]
+    async fn delta_updates_report_changed_fields() {
+        let config = DlpSyncConfig {
+            delta_updates: true,
+            ..Default::default()
+        };
+        let (requester, mut updates, local) = start(config);
+        requester.subscribe(RECORD).await.unwrap();
+
+        let mut data = record_data();
+        local.push_record(PROGRAM, RECORD, data.clone(), 10);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 10, .. }
+        ));
+
+        data[RecordField::Lamports.range()].copy_from_slice(&42u64.to_le_bytes());
+        local.push_record(PROGRAM, RECORD, data, 11);
+        match next(&mut updates).await {
+            AccountUpdate::DelegatedDelta {
+                record,
+                changed_fields,
+                slot,
+                ..
+            } => {
+                assert_eq!((record, slot), (RECORD, 11));
+                assert_eq!(
+                    changed_fields,
+                    vec![(RecordField::Lamports, 42u64.to_le_bytes().to_vec())]
+                );
+            }
+            update => panic!("unexpected update {update:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_undelegations_invoked_via_cpi() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        // Another program invokes the undelegation, which then only shows up
+        // in the inner instructions
+        let mut txn = undelegation(20, 1, RECORD);
+        let info = txn.transaction.as_mut().unwrap();
+        let message = info.transaction.as_mut().unwrap().message.as_mut().unwrap();
+        let undelegate = std::mem::replace(
+            &mut message.instructions[0],
+            CompiledInstruction {
+                program_id_index: 1,
+                accounts: Vec::new(),
+                data: Vec::new(),
+            },
+        );
+        info.meta.as_mut().unwrap().inner_instructions = vec![InnerInstructions {
+            index: 0,
+            instructions: vec![InnerInstruction {
+                program_id_index: undelegate.program_id_index,
+                accounts: undelegate.accounts,
+                data: undelegate.data,
+                stack_height: Some(2),
+            }],
+        }];
+
+        push_transaction(&local, txn);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                record: RECORD,
+                slot: 20,
+                source: UndelegationSource::Transaction,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolves_records_from_lookup_tables() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        // The record is the last account, so it moves to the loaded addresses
+        // without changing its index
+        let mut txn = undelegation(20, 1, RECORD);
+        let info = txn.transaction.as_mut().unwrap();
+        let message = info.transaction.as_mut().unwrap().message.as_mut().unwrap();
+        let record = message.account_keys.pop().unwrap();
+        message.versioned = true;
+        info.meta.as_mut().unwrap().loaded_writable_addresses = vec![record];
+
+        push_transaction(&local, txn);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                record: RECORD,
+                slot: 20,
+                source: UndelegationSource::Transaction,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn zero_health_check_timeout_times_out() {
+        // Accepts connections, but never answers them
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let config = DlpSyncConfig {
+            health_check_timeout: Some(Duration::ZERO),
+            start_timeout: None,
+            ..Default::default()
+        };
+
+        let result = DlpSyncer::start_with_endpoints(vec![endpoint], "key".into(), config).await;
+        assert!(matches!(
+            result,
+            Err(DlpSyncError::Connection("health check timed out"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn follows_a_record_through_its_lifecycle() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        local.push_record(PROGRAM, OTHER_RECORD, record_data(), 10);
+        local.push_record(PROGRAM, RECORD, record_data(), 10);
+        push_transaction(&local, undelegation(20, 1, RECORD));
+        local.push_record(PROGRAM, RECORD, record_data(), 30);
+        local.push_close(RECORD, 40);
+
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated {
+                record: RECORD,
+                slot: 10,
+                ..
+            }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                record: RECORD,
+                slot: 20,
+                source: UndelegationSource::Transaction,
+                ..
+            }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated {
+                record: RECORD,
+                slot: 30,
+                ..
+            }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Undelegated {
+                record: RECORD,
+                slot: 40,
+                source: UndelegationSource::AccountClose,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn current_slot_never_decreases() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        local.push_slot(30, Commitment::Processed);
+        local.push_slot(29, Commitment::Confirmed);
+        local.push_slot(25, Commitment::Finalized);
+        // Delivered after the slots, so they have been handled by then
+        local.push_record(PROGRAM, RECORD, record_data(), 20);
+        next(&mut updates).await;
+
+        assert_eq!(requester.current_slot().await, Some(30));
+        assert_eq!(
+            requester.current_slot_at(Commitment::Finalized).await,
+            Some(25)
+        );
+    }
+
+    #[test]
+    fn merges_channel_options() {
+        let config = DlpSyncConfig {
+            channel_options: ChannelOptions {
+                max_decoding_message_size: Some(64 * 1024 * 1024),
+                send_compression: Some(CompressionEncoding::Gzip),
+                ..Default::default()
+            },
+            send_compression: Some(CompressionEncoding::Zstd),
+            ..Default::default()
+        };
+
+        let options = DlpSyncer::laserstream_config("key".into(), &config).channel_options;
+        assert_eq!(options.max_decoding_message_size, Some(64 * 1024 * 1024));
+        // The dedicated compression setting takes precedence
+        assert!(matches!(
+            options.send_compression,
+            Some(CompressionEncoding::Zstd)
+        ));
+    }
+
+    #[tokio::test]
+    async fn drops_records_of_the_wrong_size() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        let mut data = record_data();
+        data.pop();
+        local.push_record(PROGRAM, RECORD, data, 10);
+        local.push_record(PROGRAM, RECORD, record_data(), 11);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 11, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn ignores_vote_transactions() {
+        let (requester, mut updates, local) = start(DlpSyncConfig::default());
+        requester.subscribe(RECORD).await.unwrap();
+
+        let mut vote = undelegation(20, 1, RECORD);
+        vote.transaction.as_mut().unwrap().is_vote = true;
+        push_transaction(&local, vote);
+        local.push_record(PROGRAM, RECORD, record_data(), 21);
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 21, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_with_zero_capacity_matches_the_channel() {
+        let config = DlpSyncConfig {
+            update_capacity: 0,
+            backpressure: Backpressure::DropOldest,
+            ..Default::default()
+        };
+        let (requester, mut updates, local) = start(config);
+        requester.subscribe(RECORD).await.unwrap();
+
+        // The channel and the queue hold one update each, the third evicts
+        // the queued one
+        for slot in 10..=12 {
+            local.push_record(PROGRAM, RECORD, record_data(), slot);
+        }
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 10, .. }
+        ));
+        assert!(matches!(
+            next(&mut updates).await,
+            AccountUpdate::Delegated { slot: 12, .. }
+        ));
+        assert_eq!(requester.metrics().updates_dropped(), 1);
     }
 }
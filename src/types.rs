@@ -1,4 +1,11 @@
-use helius_laserstream::LaserstreamError;
+use std::{fmt, time::Duration};
+
+use helius_laserstream::{
+    grpc::{self, SubscribeUpdate, SubscribeUpdateTransaction},
+    LaserstreamError,
+};
+
+use crate::record::{DelegationRecord, RecordField};
 
 /// Pubkey type alias for Solana public keys (32 bytes).
 pub type Pubkey = [u8; 32];
@@ -6,6 +13,9 @@ pub type Pubkey = [u8; 32];
 /// Solana slot number.
 pub type Slot = u64;
 
+/// Solana transaction signature type alias (64 bytes).
+pub type Signature = [u8; 64];
+
 /// Errors that can occur during DLP synchronization.
 #[derive(Debug)]
 pub enum DlpSyncError {
@@ -13,27 +23,659 @@ pub enum DlpSyncError {
     Connection(&'static str),
     /// Laserstream error.
     LaserStream(LaserstreamError),
+    /// A request to the running syncer failed.
+    Requester(RequesterError),
 }
 
+impl fmt::Display for DlpSyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connection(message) => f.write_str(message),
+            Self::LaserStream(error) => write!(f, "laserstream error: {error}"),
+            Self::Requester(error) => write!(f, "request failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for DlpSyncError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Connection(_) => None,
+            Self::LaserStream(error) => Some(error),
+            Self::Requester(error) => Some(error),
+        }
+    }
+}
+
+/// Errors returned by [`DlpSyncChannelsRequester`](crate::DlpSyncChannelsRequester) requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RequesterError {
+    /// The request could not be sent because the syncer has terminated.
+    SyncerGone,
+    /// The syncer dropped the request without responding, e.g. while terminating.
+    ResponseDropped,
+    /// The syncer did not respond within the request timeout.
+    Timeout,
+    /// The subscription would exceed the configured maximum number of records.
+    SubscriptionLimit {
+        /// The configured maximum.
+        limit: usize,
+    },
+}
+
+impl fmt::Display for RequesterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SyncerGone => f.write_str("syncer has terminated"),
+            Self::ResponseDropped => f.write_str("syncer dropped the request"),
+            Self::Timeout => f.write_str("syncer did not respond in time"),
+            Self::SubscriptionLimit { limit } => {
+                write!(f, "subscription limit of {limit} records reached")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RequesterError {}
+
 /// Account updates from the Laserstream.
-#[derive(Debug)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AccountUpdate {
     /// A delegation record was updated.
     Delegated {
+        /// The delegation program the record belongs to.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        program: Pubkey,
         /// The delegation record pubkey.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
         record: Pubkey,
         /// The account data.
         data: Vec<u8>,
         /// The slot at which the update occurred.
         slot: Slot,
+        /// Write version of the account, ordering updates within a slot.
+        write_version: u64,
+        /// Owner program of the record account.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        owner: Pubkey,
+    },
+    /// A delegation record was updated, with its data decoded.
+    ///
+    /// Only emitted when [`DlpSyncConfig::parse_records`](crate::DlpSyncConfig::parse_records)
+    /// is enabled; data that fails to decode is emitted as a raw `Delegated` instead.
+    DelegatedParsed {
+        /// The delegation program the record belongs to.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        program: Pubkey,
+        /// The delegation record pubkey.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        record: Pubkey,
+        /// The decoded record data.
+        parsed: DelegationRecord,
+        /// The slot at which the update occurred.
+        slot: Slot,
     },
-    /// A delegation record was undelegated.
+    /// A delegate instruction was executed by a transaction.
+    ///
+    /// Reported as soon as the transaction is seen, with context the record
+    /// data lacks; the record's account update follows as a `Delegated`.
+    DelegatedByTransaction {
+        /// The delegation program the record belongs to.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        program: Pubkey,
+        /// The delegation record pubkey.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        record: Pubkey,
+        /// The account that was delegated.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        account: Pubkey,
+        /// The program owning the delegated account.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        owner: Pubkey,
+        /// The slot of the delegating transaction.
+        slot: Slot,
+        /// Signature of the delegating transaction.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        signature: Signature,
+    },
+    /// A delegation record changed, described by the fields that differ from
+    /// the previously emitted state.
+    ///
+    /// Only emitted when [`DlpSyncConfig::delta_updates`](crate::DlpSyncConfig::delta_updates)
+    /// is enabled; the first update for a record is always a full `Delegated`.
+    DelegatedDelta {
+        /// The delegation program the record belongs to.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        program: Pubkey,
+        /// The delegation record pubkey.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        record: Pubkey,
+        /// The changed fields with their new raw bytes, in layout order.
+        changed_fields: Vec<(RecordField, Vec<u8>)>,
+        /// The slot at which the update occurred.
+        slot: Slot,
+    },
+    /// A delegation record was undelegated, or its account was closed.
+    ///
+    /// With [`DlpSyncConfig::dual_source_undelegations`](crate::DlpSyncConfig::dual_source_undelegations)
+    /// enabled, a single undelegation may be reported twice: once per [`UndelegationSource`].
     Undelegated {
+        /// The delegation program the record belongs to.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        program: Pubkey,
         /// The delegation record pubkey.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
         record: Pubkey,
         /// The slot at which the undelegation occurred.
         slot: Slot,
+        /// Where the undelegation was detected.
+        source: UndelegationSource,
+        /// Signature of the undelegating transaction, for correlation and auditing.
+        ///
+        /// `None` for undelegations detected from an account close.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58::option"))]
+        signature: Option<Signature>,
+        /// Whether the undelegation succeeded on-chain.
+        ///
+        /// Always `true` unless
+        /// [`DlpSyncConfig::include_failed_undelegations`](crate::DlpSyncConfig::include_failed_undelegations)
+        /// is enabled.
+        succeeded: bool,
+    },
+    /// Several delegation records were undelegated by the same transaction.
+    ///
+    /// Only emitted when [`DlpSyncConfig::batch_undelegations`](crate::DlpSyncConfig::batch_undelegations)
+    /// is enabled, in place of one `Undelegated` per record detected from the
+    /// transaction. Account closes are still reported per record.
+    UndelegatedBatch {
+        /// The undelegated delegation record pubkeys, in instruction order.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58::vec"))]
+        records: Vec<Pubkey>,
+        /// The slot at which the undelegation occurred.
+        slot: Slot,
+        /// Signature of the undelegating transaction.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        signature: Signature,
+        /// Whether the transaction succeeded on-chain.
+        succeeded: bool,
+    },
+    /// A transaction matched one of the extra filters added via
+    /// [`DlpSyncerBuilder::transaction_filter`](crate::DlpSyncerBuilder::transaction_filter).
+    ///
+    /// The transaction is passed through unparsed, and is skipped by the
+    /// `serde` implementations.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    RawTransaction {
+        /// Labels of the extra filters the transaction matched.
+        filters: Vec<String>,
+        /// The raw transaction update.
+        transaction: Box<SubscribeUpdateTransaction>,
+    },
+    /// An update exactly as received from the Laserstream.
+    ///
+    /// Only emitted when [`DlpSyncConfig::raw_passthrough`](crate::DlpSyncConfig::raw_passthrough)
+    /// is enabled, in addition to the typed updates. This is an escape hatch
+    /// for data the typed variants leave out, and is skipped by the `serde`
+    /// implementations.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Raw(Box<SubscribeUpdate>),
+    /// A slot changed status.
+    ///
+    /// Only emitted when [`DlpSyncConfig::slot_status_updates`](crate::DlpSyncConfig::slot_status_updates)
+    /// is enabled.
+    SlotStatus {
+        /// The slot whose status changed.
+        slot: Slot,
+        /// The new status of the slot.
+        status: SlotStatus,
+    },
+    /// A subscription was dropped after seeing no activity for the configured TTL.
+    ///
+    /// Only emitted when [`DlpSyncConfig::subscription_ttl`](crate::DlpSyncConfig::subscription_ttl)
+    /// is set. No further updates are reported for the record unless it is
+    /// subscribed again.
+    SubscriptionExpired {
+        /// The expired delegation record pubkey.
+        #[cfg_attr(feature = "serde", serde(with = "crate::base58"))]
+        record: Pubkey,
+    },
+    /// Periodic liveness signal, emitted even when no record changes.
+    ///
+    /// Only emitted when [`DlpSyncConfig::heartbeat_interval`](crate::DlpSyncConfig::heartbeat_interval)
+    /// is set.
+    Heartbeat {
+        /// The latest observed slot.
+        slot: Slot,
+    },
+    /// The bootstrap window ended and the collected delegations were emitted.
+    ///
+    /// Only emitted when [`DlpSyncConfig::bootstrap_window`](crate::DlpSyncConfig::bootstrap_window)
+    /// is set. Updates after this marker are live.
+    SnapshotComplete {
+        /// Number of delegated records emitted as part of the snapshot.
+        records: usize,
+    },
+    /// The Laserstream connection changed state.
+    ///
+    /// Emitted like any other update, without blocking the syncer, so status
+    /// events are subject to the same [`Backpressure`](crate::Backpressure) policy.
+    ConnectionStatus(ConnStatus),
+    /// The sync service has terminated, always the last update.
+    SyncTerminated(TerminationReason),
+}
+
+/// State of the Laserstream connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConnStatus {
+    /// The stream is live again after reconnecting.
+    Connected,
+    /// The stream failed and is reconnecting.
+    Reconnecting {
+        /// Number of consecutive failures, starting at 1.
+        attempt: u32,
+        /// Backoff before the next connection attempt.
+        ///
+        /// `None` when the Laserstream client reconnects on its own after a
+        /// stream error, with its own spacing.
+        delay: Option<Duration>,
     },
-    /// The sync service has terminated.
-    SyncTerminated,
+    /// The stream gave up, and no further updates will arrive.
+    Disconnected,
+}
+
+/// Why the sync service terminated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TerminationReason {
+    /// The requester was dropped, so no further requests can arrive.
+    ChannelsClosed,
+    /// The Laserstream ended without a preceding error.
+    StreamEnded,
+    /// A graceful shutdown was requested.
+    Shutdown,
+    /// The Laserstream failed for good, or the syncer task was aborted.
+    Fatal(String),
+}
+
+/// Outcome of a [`subscribe_with_state`](crate::DlpSyncChannelsRequester::subscribe_with_state) request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubscribeResult {
+    /// The latest observed slot, or the slot at the requested commitment.
+    pub slot: Slot,
+    /// Whether the syncer holds a delegated state for the record.
+    ///
+    /// The stream only carries changes, so a record subscribed for the first
+    /// time is reported as not delegated until an update for it arrives.
+    pub currently_delegated: bool,
+}
+
+/// Point-in-time health report of a running syncer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HealthStatus {
+    /// Whether the Laserstream connection is currently live.
+    pub connected: bool,
+    /// The first slot observed during this session, if any.
+    pub first_slot: Option<Slot>,
+    /// The most recent slot observed.
+    pub current_slot: Slot,
+    /// Number of tracked delegation records.
+    pub subscriptions: usize,
+    /// Number of updates waiting for the consumer, in the channel and queued.
+    pub pending_updates: usize,
+}
+
+/// Commitment level of a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Commitment {
+    /// The slot has been processed by the node.
+    Processed,
+    /// The slot has been voted on by a supermajority of the cluster.
+    Confirmed,
+    /// The slot has been rooted and cannot be rolled back.
+    Finalized,
+}
+
+/// Status transition of a slot, as reported by the Laserstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SlotStatus {
+    /// The slot has been processed.
+    Processed,
+    /// The slot has been confirmed.
+    Confirmed,
+    /// The slot has been finalized.
+    Finalized,
+    /// The first shred of the slot was received.
+    FirstShredReceived,
+    /// All shreds of the slot were received.
+    Completed,
+    /// A bank was created for the slot.
+    CreatedBank,
+    /// The slot was abandoned and will never be confirmed.
+    Dead,
+}
+
+impl From<Commitment> for grpc::CommitmentLevel {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => Self::Processed,
+            Commitment::Confirmed => Self::Confirmed,
+            Commitment::Finalized => Self::Finalized,
+        }
+    }
+}
+
+impl SlotStatus {
+    /// The commitment level this status represents, if any.
+    pub fn commitment(self) -> Option<Commitment> {
+        match self {
+            Self::Processed => Some(Commitment::Processed),
+            Self::Confirmed => Some(Commitment::Confirmed),
+            Self::Finalized => Some(Commitment::Finalized),
+            _ => None,
+        }
+    }
+}
+
+impl From<grpc::SlotStatus> for SlotStatus {
+    fn from(status: grpc::SlotStatus) -> Self {
+        use grpc::SlotStatus::*;
+
+        match status {
+            SlotProcessed => Self::Processed,
+            SlotConfirmed => Self::Confirmed,
+            SlotFinalized => Self::Finalized,
+            SlotFirstShredReceived => Self::FirstShredReceived,
+            SlotCompleted => Self::Completed,
+            SlotCreatedBank => Self::CreatedBank,
+            SlotDead => Self::Dead,
+        }
+    }
+}
+
+/// Origin of an [`AccountUpdate::Undelegated`] event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UndelegationSource {
+    /// Detected from an undelegate instruction in a transaction.
+    Transaction,
+    /// Detected from the delegation record account being closed.
+    AccountClose,
+}
+
+impl AccountUpdate {
+    /// The slot of a stream-derived update, if any.
+    ///
+    /// Slot status updates are left out, as they drive the slot ordering
+    /// rather than being subject to it.
+    pub(crate) fn slot(&self) -> Option<Slot> {
+        match self {
+            Self::Delegated { slot, .. }
+            | Self::DelegatedParsed { slot, .. }
+            | Self::DelegatedDelta { slot, .. }
+            | Self::DelegatedByTransaction { slot, .. }
+            | Self::Undelegated { slot, .. }
+            | Self::UndelegatedBatch { slot, .. } => Some(*slot),
+            Self::RawTransaction { transaction, .. } => Some(transaction.slot),
+            _ => None,
+        }
+    }
+
+    /// The delegation record this update concerns, if any.
+    pub(crate) fn record(&self) -> Option<&Pubkey> {
+        match self {
+            Self::Delegated { record, .. }
+            | Self::DelegatedParsed { record, .. }
+            | Self::DelegatedDelta { record, .. }
+            | Self::DelegatedByTransaction { record, .. }
+            | Self::Undelegated { record, .. } => Some(record),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for AccountUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Delegated {
+                program,
+                record,
+                data,
+                slot,
+                write_version,
+                owner,
+            } => f
+                .debug_struct("Delegated")
+                .field("program", &PubkeyDisplay(program))
+                .field("record", &PubkeyDisplay(record))
+                .field("data", data)
+                .field("slot", slot)
+                .field("write_version", write_version)
+                .field("owner", &PubkeyDisplay(owner))
+                .finish(),
+            Self::DelegatedParsed {
+                program,
+                record,
+                parsed,
+                slot,
+            } => f
+                .debug_struct("DelegatedParsed")
+                .field("program", &PubkeyDisplay(program))
+                .field("record", &PubkeyDisplay(record))
+                .field("parsed", parsed)
+                .field("slot", slot)
+                .finish(),
+            Self::DelegatedDelta {
+                program,
+                record,
+                changed_fields,
+                slot,
+            } => f
+                .debug_struct("DelegatedDelta")
+                .field("program", &PubkeyDisplay(program))
+                .field("record", &PubkeyDisplay(record))
+                .field("changed_fields", changed_fields)
+                .field("slot", slot)
+                .finish(),
+            Self::DelegatedByTransaction {
+                program,
+                record,
+                account,
+                owner,
+                slot,
+                signature,
+            } => f
+                .debug_struct("DelegatedByTransaction")
+                .field("program", &PubkeyDisplay(program))
+                .field("record", &PubkeyDisplay(record))
+                .field("account", &PubkeyDisplay(account))
+                .field("owner", &PubkeyDisplay(owner))
+                .field("slot", slot)
+                .field("signature", &bs58::encode(signature).into_string())
+                .finish(),
+            Self::Undelegated {
+                program,
+                record,
+                slot,
+                source,
+                signature,
+                succeeded,
+            } => f
+                .debug_struct("Undelegated")
+                .field("program", &PubkeyDisplay(program))
+                .field("record", &PubkeyDisplay(record))
+                .field("slot", slot)
+                .field("source", source)
+                .field(
+                    "signature",
+                    &signature.map(|s| bs58::encode(s).into_string()),
+                )
+                .field("succeeded", succeeded)
+                .finish(),
+            Self::UndelegatedBatch {
+                records,
+                slot,
+                signature,
+                succeeded,
+            } => f
+                .debug_struct("UndelegatedBatch")
+                .field(
+                    "records",
+                    &records.iter().map(PubkeyDisplay).collect::<Vec<_>>(),
+                )
+                .field("slot", slot)
+                .field("signature", &bs58::encode(signature).into_string())
+                .field("succeeded", succeeded)
+                .finish(),
+            Self::RawTransaction {
+                filters,
+                transaction,
+            } => f
+                .debug_struct("RawTransaction")
+                .field("filters", filters)
+                .field("slot", &transaction.slot)
+                .finish_non_exhaustive(),
+            Self::Raw(update) => f
+                .debug_struct("Raw")
+                .field("filters", &update.filters)
+                .finish_non_exhaustive(),
+            Self::SlotStatus { slot, status } => f
+                .debug_struct("SlotStatus")
+                .field("slot", slot)
+                .field("status", status)
+                .finish(),
+            Self::SubscriptionExpired { record } => f
+                .debug_struct("SubscriptionExpired")
+                .field("record", &PubkeyDisplay(record))
+                .finish(),
+            Self::Heartbeat { slot } => f.debug_struct("Heartbeat").field("slot", slot).finish(),
+            Self::SnapshotComplete { records } => f
+                .debug_struct("SnapshotComplete")
+                .field("records", records)
+                .finish(),
+            Self::ConnectionStatus(status) => {
+                f.debug_tuple("ConnectionStatus").field(status).finish()
+            }
+            Self::SyncTerminated(reason) => f.debug_tuple("SyncTerminated").field(reason).finish(),
+        }
+    }
+}
+
+/// Parses a base58-encoded delegation program id.
+///
+/// Malformed input yields a [`DlpSyncError::Connection`] rather than a panic,
+/// so program ids read from configuration can be validated at runtime.
+pub fn parse_program_id(encoded: &str) -> Result<Pubkey, DlpSyncError> {
+    let bytes = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|_| DlpSyncError::Connection("invalid program id: not valid base58"))?;
+    Pubkey::try_from(bytes.as_slice())
+        .map_err(|_| DlpSyncError::Connection("invalid program id: expected 32 bytes"))
+}
+
+/// Formats a [`Pubkey`] as base58 in both `Display` and `Debug` output.
+pub(crate) struct PubkeyDisplay<'a>(pub(crate) &'a Pubkey);
+
+impl fmt::Display for PubkeyDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bs58::encode(self.0).into_string())
+    }
+}
+
+impl fmt::Debug for PubkeyDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `Delegated` update with the given record data.
+    fn delegated(data: Vec<u8>) -> AccountUpdate {
+        AccountUpdate::Delegated {
+            program: [0; 32],
+            record: [1; 32],
+            data,
+            slot: 3,
+            write_version: 4,
+            owner: [0; 32],
+        }
+    }
+
+    #[test]
+    fn debug_renders_pubkeys_as_base58() {
+        let update = delegated(vec![2]);
+        assert_eq!(
+            format!("{update:?}"),
+            "Delegated { program: 11111111111111111111111111111111, \
+             record: 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi, data: [2], slot: 3, \
+             write_version: 4, owner: 11111111111111111111111111111111 }"
+        );
+    }
+
+    #[test]
+    fn debug_renders_health_status() {
+        let health = HealthStatus {
+            connected: true,
+            first_slot: Some(10),
+            current_slot: 12,
+            subscriptions: 3,
+            pending_updates: 1,
+        };
+        assert_eq!(
+            format!("{health:?}"),
+            "HealthStatus { connected: true, first_slot: Some(10), current_slot: 12, \
+             subscriptions: 3, pending_updates: 1 }"
+        );
+    }
+
+    #[test]
+    fn structurally_equal_updates_compare_equal() {
+        let update = delegated(vec![2]);
+        assert_eq!(update, delegated(vec![2]));
+        assert_eq!(update.clone(), update);
+        assert_ne!(update, delegated(vec![3]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips_with_base58_keys() {
+        let update = delegated(vec![2]);
+        let json = serde_json::to_string(&update).unwrap();
+        assert!(json.contains("\"4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi\""));
+        assert_eq!(
+            serde_json::from_str::<AccountUpdate>(&json).unwrap(),
+            update
+        );
+    }
+
+    #[test]
+    fn parses_valid_program_ids() {
+        let program = parse_program_id("DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh").unwrap();
+        assert_eq!(program, crate::syncer::DELEGATION_PROGRAM_PUBKEY);
+    }
+
+    #[test]
+    fn rejects_malformed_program_ids() {
+        let too_short = bs58::encode([1; 31]).into_string();
+        let too_long = bs58::encode([1; 33]).into_string();
+        for (encoded, expected) in [
+            (too_short.as_str(), "invalid program id: expected 32 bytes"),
+            (too_long.as_str(), "invalid program id: expected 32 bytes"),
+            ("0OIl", "invalid program id: not valid base58"),
+        ] {
+            assert!(matches!(
+                parse_program_id(encoded),
+                Err(DlpSyncError::Connection(message)) if message == expected
+            ));
+        }
+    }
 }
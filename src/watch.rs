@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use crate::channels::DlpSyncChannelsInit;
+use crate::syncer::DlpSyncer;
+use crate::types::{AccountUpdate, DlpSyncError, Pubkey, PubkeyDisplay};
+
+/// Starts a syncer and prints every update to stdout until it terminates.
+///
+/// Intended for smoke-testing an endpoint and API key without writing a
+/// consumer. Subscribes to the given base58 record pubkeys, or runs in
+/// firehose mode if none are given.
+///
+/// # Arguments
+///
+/// * `endpoint` - The Laserstream gRPC endpoint URL.
+/// * `key` - The API key for authentication.
+/// * `records` - Base58-encoded delegation record pubkeys to watch.
+///
+/// # Returns
+///
+/// Returns `Ok(())` once the sync service terminates, or a [`DlpSyncError`]
+/// if a record pubkey is malformed, the connection fails or a subscription
+/// is rejected, so callers can simply exit with a non-zero status on error.
+/// The rejected record is logged at warn level.
+pub async fn watch(endpoint: String, key: String, records: Vec<&str>) -> Result<(), DlpSyncError> {
+    let records = records
+        .into_iter()
+        .map(|r| decode_pubkey(r).ok_or(DlpSyncError::Connection("invalid record pubkey")))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let channels = DlpSyncer::builder()
+        .endpoint(endpoint)
+        .api_key(key)
+        .firehose(records.is_empty())
+        .build()
+        .await?;
+    print_updates(channels, records, &mut io::stdout()).await
+}
+
+/// Subscribes to `records` and writes every update to `out` until the syncer terminates.
+async fn print_updates(
+    channels: DlpSyncChannelsInit,
+    records: Vec<Pubkey>,
+    out: &mut impl Write,
+) -> Result<(), DlpSyncError> {
+    let (requester, mut updates) = channels.split();
+
+    for record in records {
+        let slot = requester.subscribe(record).await.map_err(|error| {
+            tracing::warn!(record = %PubkeyDisplay(&record), %error, "failed to subscribe");
+            DlpSyncError::Requester(error)
+        })?;
+        let _ = writeln!(out, "subscribed at slot {slot}");
+    }
+
+    while let Some(update) = updates.recv().await {
+        let _ = writeln!(out, "{update:?}");
+        if matches!(update, AccountUpdate::SyncTerminated(_)) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a base58-encoded pubkey, returning `None` if it is malformed.
+fn decode_pubkey(encoded: &str) -> Option<Pubkey> {
+    let bytes = bs58::decode(encoded).into_vec().ok()?;
+    Pubkey::try_from(bytes.as_slice()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DlpSyncConfig;
+    use crate::record::DELEGATION_RECORD_SIZE;
+    use crate::syncer::DELEGATION_PROGRAM_PUBKEY;
+    use crate::types::RequesterError;
+
+    /// A watched delegation record.
+    const RECORD: Pubkey = [7; 32];
+
+    #[tokio::test]
+    async fn prints_updates_until_terminated() {
+        let (channels, local) = DlpSyncer::start_local(DlpSyncConfig::default());
+        let data = vec![1; DELEGATION_RECORD_SIZE as usize];
+        let requester = crate::channels::DlpSyncChannels {
+            requests: channels.requests.clone(),
+            updates: (),
+            connected: channels.connected.clone(),
+            request_timeout: None,
+            delegation_programs: channels.delegation_programs.clone(),
+            metrics: channels.metrics.clone(),
+            task: None,
+        };
+        let mut out = Vec::new();
+
+        let printing = print_updates(channels, vec![RECORD], &mut out);
+        let pushing = async {
+            while requester.list_subscriptions().await != Some(vec![RECORD]) {
+                tokio::task::yield_now().await;
+            }
+            local.push_record(DELEGATION_PROGRAM_PUBKEY, RECORD, data, 10);
+            drop(local);
+        };
+        let (result, ()) = tokio::join!(printing, pushing);
+        result.unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "subscribed at slot 0");
+        assert!(lines[1].starts_with("Delegated {"));
+        assert!(lines[1].contains(&format!("record: {}", PubkeyDisplay(&RECORD))));
+        assert!(lines.last().unwrap().starts_with("SyncTerminated("));
+    }
+
+    #[tokio::test]
+    async fn fails_on_rejected_subscriptions() {
+        let config = DlpSyncConfig {
+            max_subscriptions: 0,
+            ..Default::default()
+        };
+        let (channels, _local) = DlpSyncer::start_local(config);
+
+        let result = print_updates(channels, vec![RECORD], &mut Vec::new()).await;
+        assert!(matches!(
+            result,
+            Err(DlpSyncError::Requester(RequesterError::SubscriptionLimit {
+                limit: 0
+            }))
+        ));
+    }
+
+    #[test]
+    fn decodes_base58_pubkeys() {
+        let encoded = PubkeyDisplay(&RECORD).to_string();
+        assert_eq!(decode_pubkey(&encoded), Some(RECORD));
+        assert_eq!(decode_pubkey("0OIl"), None);
+        assert_eq!(decode_pubkey(&bs58::encode([7; 31]).into_string()), None);
+    }
+}